@@ -0,0 +1,336 @@
+use crate::domain::AssetsConfig;
+use crate::error::{BlogError, IoResultExt, Result};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+struct Piece {
+    source: String,
+    source_content: String,
+    output: String,
+    line_accurate: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct CompiledAssets {
+    pub urls: BTreeMap<String, String>,
+    pub generated: BTreeMap<String, String>,
+    pub copied: BTreeMap<String, PathBuf>,
+}
+
+pub fn process_assets(assets_dir: &Path, output_dir: &Path, config: &AssetsConfig, dev: bool) -> Result<BTreeMap<String, String>> {
+    let compiled = compile_assets(assets_dir, config, dev)?;
+    for (relative, content) in &compiled.generated {
+        let target = output_dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).io("create", parent)?;
+        }
+        fs::write(&target, content).io("write asset", &target)?;
+    }
+    for (relative, source) in &compiled.copied {
+        let target = output_dir.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).io("create", parent)?;
+        }
+        fs::copy(source, &target).io("copy asset", source)?;
+    }
+    Ok(compiled.urls)
+}
+
+pub fn compile_assets(assets_dir: &Path, config: &AssetsConfig, dev: bool) -> Result<CompiledAssets> {
+    let mut compiled = CompiledAssets::default();
+    if !assets_dir.exists() && config.bundles.is_empty() {
+        return Ok(compiled);
+    }
+
+    let minify = config.minify.unwrap_or(true) && !dev;
+    let bundled: HashSet<&str> = config
+        .bundles
+        .iter()
+        .flat_map(|bundle| bundle.files.iter().map(|f| f.trim_start_matches('/')))
+        .collect();
+
+    for entry in WalkDir::new(assets_dir).into_iter().filter_map(|e| e.ok()) {
+        let path = entry.path();
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = relative_name(assets_dir, path);
+        if bundled.contains(relative.as_str()) {
+            continue;
+        }
+
+        let file_name = entry.file_name().to_string_lossy();
+        match extension(&relative) {
+            "scss" | "sass" if file_name.starts_with('_') => continue,
+            "scss" | "sass" => {
+                let piece = compile_piece(assets_dir, &relative, minify)?;
+                let output = format!("{}.css", relative.rsplit_once('.').map_or(relative.as_str(), |(stem, _)| stem));
+                emit_output(&mut compiled.generated, &output, vec![piece], minify, dev);
+                compiled.urls.insert(output.clone(), format!("/{}", output));
+            }
+            "css" | "js" => {
+                let piece = compile_piece(assets_dir, &relative, minify)?;
+                emit_output(&mut compiled.generated, &relative, vec![piece], minify, false);
+                compiled.urls.insert(relative.clone(), format!("/{}", relative));
+            }
+            _ => {
+                compiled.copied.insert(relative.clone(), path.to_path_buf());
+                compiled.urls.insert(relative.clone(), format!("/{}", relative));
+            }
+        }
+    }
+
+    for bundle in &config.bundles {
+        let output = bundle.output.trim_start_matches('/').to_string();
+        let kind = extension(&output);
+        if kind != "css" && kind != "js" {
+            return Err(BlogError::Asset {
+                path: output.into(),
+                message: format!("Asset bundle `{}` must produce a .css or .js file", bundle.output),
+            });
+        }
+
+        let mut pieces = Vec::new();
+        for file in &bundle.files {
+            let relative = file.trim_start_matches('/');
+            let compatible = match kind {
+                "css" => matches!(extension(relative), "css" | "scss" | "sass"),
+                _ => extension(relative) == "js",
+            };
+            if !compatible {
+                return Err(BlogError::Asset {
+                    path: output.into(),
+                    message: format!("Asset bundle `{}` cannot include `{}`", bundle.output, file),
+                });
+            }
+            pieces.push(compile_piece(assets_dir, relative, minify).map_err(|e| BlogError::Asset {
+                path: output.clone().into(),
+                message: format!("Failed to build asset bundle `{}`: {}", bundle.output, e.report()),
+            })?);
+        }
+
+        emit_output(&mut compiled.generated, &output, pieces, minify, dev);
+        compiled.urls.insert(output.clone(), format!("/{}", output));
+    }
+
+    Ok(compiled)
+}
+
+fn relative_name(base: &Path, path: &Path) -> String {
+    path.strip_prefix(base)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn extension(name: &str) -> &str {
+    Path::new(name).extension().and_then(|e| e.to_str()).unwrap_or("")
+}
+
+fn compile_piece(assets_dir: &Path, relative: &str, compressed: bool) -> Result<Piece> {
+    let path = assets_dir.join(relative);
+    let source_content = fs::read_to_string(&path).io("read asset", &path)?;
+
+    let (output, line_accurate) = match extension(relative) {
+        "scss" | "sass" => {
+            let style = if compressed {
+                grass::OutputStyle::Compressed
+            } else {
+                grass::OutputStyle::Expanded
+            };
+            let options = grass::Options::default().style(style).load_path(assets_dir);
+            let css = grass::from_path(&path, &options).map_err(|e| BlogError::Asset {
+                message: format!("Failed to compile {:?}: {}", path, e),
+                path: path.clone(),
+            })?;
+            (css, false)
+        }
+        _ => (source_content.clone(), true),
+    };
+
+    Ok(Piece {
+        source: format!("/assets/{}", relative),
+        source_content,
+        output,
+        line_accurate,
+    })
+}
+
+fn emit_output(generated: &mut BTreeMap<String, String>, relative: &str, pieces: Vec<Piece>, minify: bool, source_map: bool) {
+    let is_css = extension(relative) == "css";
+    let mut content = pieces
+        .iter()
+        .map(|p| p.output.trim_end_matches('\n'))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if minify {
+        content = if is_css {
+            crate::minify::css(&content)
+        } else {
+            crate::minify::js(&content)
+        };
+    }
+
+    if source_map && !minify {
+        let file_name = relative.rsplit('/').next().unwrap_or(relative);
+        generated.insert(format!("{}.map", relative), build_source_map(file_name, &pieces));
+        if is_css {
+            content.push_str(&format!("\n/*# sourceMappingURL={}.map */", file_name));
+        } else {
+            content.push_str(&format!("\n//# sourceMappingURL={}.map", file_name));
+        }
+    }
+
+    generated.insert(relative.to_string(), content);
+}
+
+fn build_source_map(file_name: &str, pieces: &[Piece]) -> String {
+    let mut mappings = Vec::new();
+    let mut previous_source = 0i64;
+    let mut previous_line = 0i64;
+
+    for (index, piece) in pieces.iter().enumerate() {
+        let generated_lines = piece.output.trim_end_matches('\n').lines().count().max(1);
+        for line in 0..generated_lines {
+            let source_line = if piece.line_accurate { line as i64 } else { 0 };
+            let mut segment = String::new();
+            encode_vlq(&mut segment, 0);
+            encode_vlq(&mut segment, index as i64 - previous_source);
+            encode_vlq(&mut segment, source_line - previous_line);
+            encode_vlq(&mut segment, 0);
+            mappings.push(segment);
+            previous_source = index as i64;
+            previous_line = source_line;
+        }
+    }
+
+    serde_json::json!({
+        "version": 3,
+        "file": file_name,
+        "sources": pieces.iter().map(|p| &p.source).collect::<Vec<_>>(),
+        "sourcesContent": pieces.iter().map(|p| &p.source_content).collect::<Vec<_>>(),
+        "names": [],
+        "mappings": mappings.join(";"),
+    })
+    .to_string()
+}
+
+fn encode_vlq(out: &mut String, value: i64) {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut vlq = if value < 0 { ((-value) << 1) | 1 } else { value << 1 };
+    loop {
+        let mut digit = vlq & 0b11111;
+        vlq >>= 5;
+        if vlq > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if vlq == 0 {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::BundleConfig;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, AssetsConfig) {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let assets = temp_dir.path().join("assets");
+        fs::create_dir_all(assets.join("scss")).unwrap();
+        fs::create_dir_all(assets.join("js")).unwrap();
+        fs::create_dir_all(assets.join("img")).unwrap();
+
+        fs::write(assets.join("scss/_vars.scss"), "$accent: #336699;\n").unwrap();
+        fs::write(assets.join("scss/main.scss"), "@use 'vars';\n.nav {\n  a { color: vars.$accent; }\n}\n").unwrap();
+        fs::write(assets.join("scss/print.scss"), ".page { margin: 0; }\n").unwrap();
+        fs::write(assets.join("reset.css"), "/* reset */\nbody {\n  margin : 0;\n}\n").unwrap();
+        fs::write(assets.join("js/a.js"), "// a\nconst a = 1;\n").unwrap();
+        fs::write(assets.join("js/b.js"), "const b = a + 1;\n").unwrap();
+        fs::write(assets.join("img/logo.svg"), "<svg/>").unwrap();
+
+        let config = AssetsConfig {
+            bundles: vec![
+                BundleConfig {
+                    output: "css/site.css".to_string(),
+                    files: vec!["reset.css".to_string(), "scss/main.scss".to_string()],
+                },
+                BundleConfig {
+                    output: "js/site.js".to_string(),
+                    files: vec!["js/a.js".to_string(), "js/b.js".to_string()],
+                },
+            ],
+            ..Default::default()
+        };
+        (temp_dir, config)
+    }
+
+    #[test]
+    fn test_process_assets() {
+        let (temp_dir, config) = setup();
+        let assets = temp_dir.path().join("assets");
+        let output = temp_dir.path().join("public");
+
+        let urls = process_assets(&assets, &output, &config, false).expect("Failed to process assets");
+
+        assert_eq!(
+            urls.keys().map(String::as_str).collect::<Vec<_>>(),
+            vec!["css/site.css", "img/logo.svg", "js/site.js", "scss/print.css"]
+        );
+        assert_eq!(urls["css/site.css"], "/css/site.css");
+
+        assert_eq!(fs::read_to_string(output.join("css/site.css")).unwrap(), "body{margin:0}.nav a{color:#369}");
+        assert_eq!(fs::read_to_string(output.join("js/site.js")).unwrap(), "const a=1;\nconst b=a+1;");
+        assert_eq!(fs::read_to_string(output.join("scss/print.css")).unwrap(), ".page{margin:0}");
+        assert!(output.join("img/logo.svg").exists());
+        assert!(!output.join("reset.css").exists());
+        assert!(!output.join("scss/_vars.css").exists());
+        assert!(!output.join("css/site.css.map").exists());
+    }
+
+    #[test]
+    fn test_process_assets_dev_source_maps() {
+        let (temp_dir, config) = setup();
+        let assets = temp_dir.path().join("assets");
+        let output = temp_dir.path().join("public");
+
+        process_assets(&assets, &output, &config, true).expect("Failed to process assets");
+
+        let js = fs::read_to_string(output.join("js/site.js")).unwrap();
+        assert!(js.starts_with("// a\nconst a = 1;\nconst b = a + 1;"), "{}", js);
+        assert!(js.ends_with("//# sourceMappingURL=site.js.map"));
+
+        let map: serde_json::Value = serde_json::from_str(&fs::read_to_string(output.join("js/site.js.map")).unwrap()).unwrap();
+        assert_eq!(map["sources"], serde_json::json!(["/assets/js/a.js", "/assets/js/b.js"]));
+        assert_eq!(map["mappings"], "AAAA;AACA;ACDA");
+
+        let css = fs::read_to_string(output.join("css/site.css")).unwrap();
+        assert!(css.contains("margin : 0"));
+        assert!(css.ends_with("/*# sourceMappingURL=site.css.map */"));
+    }
+
+    #[test]
+    fn test_bundle_errors() {
+        let (temp_dir, mut config) = setup();
+        let assets = temp_dir.path().join("assets");
+        let output = temp_dir.path().join("public");
+
+        config.bundles[1].files.push("reset.css".to_string());
+        let err = process_assets(&assets, &output, &config, false).unwrap_err().to_string();
+        assert!(err.contains("cannot include `reset.css`"), "{}", err);
+
+        config.bundles[1].files.pop();
+        fs::write(assets.join("scss/main.scss"), ".nav { color: $missing; }").unwrap();
+        let err = process_assets(&assets, &output, &config, false).unwrap_err().report();
+        assert!(err.contains("css/site.css"), "{}", err);
+        assert!(err.contains("Undefined variable"), "{}", err);
+    }
+}
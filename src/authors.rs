@@ -0,0 +1,122 @@
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::domain::{Author, AuthorProfile, Config, Post};
+use crate::error::{BlogError, IoResultExt, Result};
+use crate::functions::slugify;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+pub const AUTHORS_FILE: &str = "data/authors.toml";
+
+pub fn load_authors(project_dir: &Path, config: &mut Config) -> Result<()> {
+    let path = project_dir.join(AUTHORS_FILE);
+    if !path.is_file() {
+        return Ok(());
+    }
+    let content = fs::read_to_string(&path).io("read", &path)?;
+    let profiles: BTreeMap<String, AuthorProfile> = toml::from_str(&content).map_err(|source| BlogError::Config {
+        path: path.clone(),
+        source,
+    })?;
+    for (id, profile) in profiles {
+        if config.authors.contains_key(&id) {
+            return Err(BlogError::InvalidConfig(format!(
+                "Author `{}` is defined in both config.toml and {}",
+                id, AUTHORS_FILE
+            )));
+        }
+        config.authors.insert(id, profile);
+    }
+    Ok(())
+}
+
+pub fn author_url(config: &Config, lang: &str, id: &str) -> String {
+    format!("{}/authors/{}/", config.language_prefix(lang), slugify(id))
+}
+
+pub fn resolve_authors(posts: &mut [Post], config: &Config, diagnostics: &mut Diagnostics) {
+    for post in posts.iter_mut() {
+        let ids = post.meta.authors.clone().unwrap_or_default();
+        post.authors = ids
+            .into_iter()
+            .map(|id| match config.authors.get(&id) {
+                Some(profile) => Author {
+                    url: author_url(config, &post.lang, &id),
+                    name: profile.name.clone(),
+                    bio: profile.bio.clone(),
+                    avatar: profile.avatar.clone(),
+                    email: profile.email.clone(),
+                    links: profile.links.clone(),
+                    id,
+                },
+                None => {
+                    diagnostics.push(Diagnostic::warning(
+                        &crate::engine::post_source(post),
+                        format!("Author `{}` has no profile in config.toml or {}", id, AUTHORS_FILE),
+                    ));
+                    Author {
+                        url: author_url(config, &post.lang, &id),
+                        name: id.clone(),
+                        id,
+                        ..Default::default()
+                    }
+                }
+            })
+            .collect();
+    }
+}
+
+pub fn group_by_author(posts: &[Post]) -> Vec<(&Author, Vec<&Post>)> {
+    let mut groups: BTreeMap<&str, (&Author, Vec<&Post>)> = BTreeMap::new();
+    for post in posts {
+        for author in &post.authors {
+            groups.entry(&author.id).or_insert_with(|| (author, Vec::new())).1.push(post);
+        }
+    }
+    groups.into_values().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn post(slug: &str, authors: &[&str]) -> Post {
+        let mut post = Post::fixture(slug);
+        post.meta.authors = Some(authors.iter().map(|a| a.to_string()).collect());
+        post
+    }
+
+    #[test]
+    fn test_load_and_resolve_authors() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        fs::create_dir_all(temp_dir.path().join("data")).unwrap();
+        fs::write(
+            temp_dir.path().join(AUTHORS_FILE),
+            "[ada]\nname = \"Ada Lovelace\"\nbio = \"Analyst\"\nlinks = { github = \"https://github.com/ada\" }\n",
+        )
+        .unwrap();
+        let mut config: Config = toml::from_str("title = \"T\"\nbase_url = \"/\"\n[authors.grace]\nname = \"Grace Hopper\"\n").unwrap();
+        load_authors(temp_dir.path(), &mut config).expect("Failed to load authors");
+        assert_eq!(config.authors.keys().collect::<Vec<_>>(), vec!["ada", "grace"]);
+
+        let mut posts = vec![post("a", &["ada", "grace"]), post("b", &["ada", "Nobody Known"]), post("c", &[])];
+        let mut diagnostics = Diagnostics::default();
+        resolve_authors(&mut posts, &config, &mut diagnostics);
+
+        assert_eq!(posts[0].authors[0].name, "Ada Lovelace");
+        assert_eq!(posts[0].authors[0].url, "/authors/ada/");
+        assert_eq!(posts[0].authors[0].links["github"], "https://github.com/ada");
+        assert_eq!(posts[1].authors[1].name, "Nobody Known");
+        assert_eq!(posts[1].authors[1].url, "/authors/nobody-known/");
+        assert_eq!(diagnostics.warning_count(), 1);
+
+        let groups: Vec<(&str, Vec<&str>)> = group_by_author(&posts)
+            .into_iter()
+            .map(|(author, posts)| (author.id.as_str(), posts.iter().map(|p| p.meta.slug.as_str()).collect()))
+            .collect();
+        assert_eq!(groups, vec![("Nobody Known", vec!["b"]), ("ada", vec!["a", "b"]), ("grace", vec!["a"])]);
+
+        assert!(matches!(load_authors(temp_dir.path(), &mut config), Err(BlogError::InvalidConfig(_))));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::domain::ImagesConfig;
+use crate::engine::{load_site, post_path, render_pages, Site};
+use crate::error::{BlogError, Result};
+use crate::images::ImageProcessor;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::Arc;
+use walkdir::WalkDir;
+
+#[derive(Debug, Default, Clone)]
+pub struct CheckOptions {
+    pub include_drafts: bool,
+}
+
+pub fn check_site<P: AsRef<Path>>(project_dir: P, options: &CheckOptions) -> Result<Diagnostics> {
+    let project_dir = project_dir.as_ref();
+    let mut diagnostics = Diagnostics::default();
+
+    let Site {
+        config,
+        mut renderer,
+        posts,
+        diagnostics: content,
+    } = match load_site(project_dir, options.include_drafts) {
+        Ok(site) => site,
+        Err(BlogError::Data { path, line, column, message }) => {
+            diagnostics.push(Diagnostic::error(&path, message).at(line, column));
+            return Ok(diagnostics);
+        }
+        Err(e) => {
+            let file = match &e {
+                BlogError::Io { path, .. } | BlogError::Config { path, .. } | BlogError::ThemeManifest { path, .. } => path.clone(),
+                BlogError::Theme { .. } | BlogError::TemplateLoad { .. } => project_dir.join("themes"),
+                _ => project_dir.join("config.toml"),
+            };
+            diagnostics.push(Diagnostic::error(&file, e.report()));
+            return Ok(diagnostics);
+        }
+    };
+    diagnostics.extend(content);
+
+    let mut files = BTreeSet::new();
+    let static_dir = project_dir.join("static");
+    files.extend(relative_files(&static_dir));
+    for post in &posts {
+        files.extend(post.resources.iter().map(|r| r.url.trim_start_matches('/').to_string()));
+    }
+
+    match crate::assets::compile_assets(&project_dir.join("assets"), &config.assets, false) {
+        Ok(compiled) => {
+            files.extend(compiled.generated.keys().cloned());
+            files.extend(compiled.copied.keys().cloned());
+            let known = compiled.urls.keys().map(|name| (name.clone(), name.clone())).collect();
+            renderer.register_site(&posts, &config, vec![static_dir], known);
+            renderer.set_assets(compiled.urls);
+        }
+        Err(e) => {
+            let file = match &e {
+                BlogError::Asset { path, .. } | BlogError::Io { path, .. } => path.clone(),
+                _ => project_dir.join("assets"),
+            };
+            diagnostics.push(Diagnostic::error(&file, e.report()));
+            renderer.register_site(&posts, &config, vec![static_dir], BTreeMap::new());
+        }
+    }
+
+    let images = ImagesConfig {
+        enabled: Some(false),
+        ..config.images.clone()
+    };
+    match ImageProcessor::new(project_dir, &project_dir.join(".cache/images"), &images) {
+        Ok(images) => renderer.register_images(Arc::new(images)),
+        Err(e) => diagnostics.push(Diagnostic::error(&project_dir.join("config.toml"), e.report())),
+    }
+
+    let pages = render_pages(&renderer, &posts, &config, &mut diagnostics)?;
+    files.extend(pages.keys().cloned());
+
+    files.extend(posts.iter().map(post_path));
+    let report = crate::links::check_links(&pages, &files, &config.base_url);
+    diagnostics.extend(report.diagnostics(&posts, true));
+
+    diagnostics.sort();
+    Ok(diagnostics)
+}
+
+fn relative_files(dir: &Path) -> Vec<String> {
+    WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let relative = e.path().strip_prefix(dir).ok()?;
+            Some(
+                relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/"),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostics::Severity;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn setup() -> TempDir {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path();
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        fs::create_dir_all(project_dir.join("static/img")).unwrap();
+        fs::write(project_dir.join("config.toml"), "title = \"Test\"\nbase_url = \"https://example.com\"\n").unwrap();
+        fs::write(project_dir.join("static/img/logo.png"), "png").unwrap();
+        fs::write(
+            project_dir.join("themes/default/index.html"),
+            "<img src=\"/img/logo.png\">{% for post in paginator.items %}<a href=\"/posts/{{ post.meta.slug }}/\">{{ post.meta.title }}</a>{% endfor %}",
+        )
+        .unwrap();
+        fs::write(project_dir.join("themes/default/post.html"), "<h1>{{ post.meta.title }}</h1>{{ post.content | safe }}").unwrap();
+        fs::write(project_dir.join("content/posts/a.md"), "---\ntitle: A\ndate: 2023-01-01\nslug: a\n---\nSee [B](/posts/b/).\n").unwrap();
+        fs::write(project_dir.join("content/posts/b.md"), "---\ntitle: B\ndate: 2023-01-02\nslug: b\n---\nBack to [A](../a/).\n").unwrap();
+        temp_dir
+    }
+
+    #[test]
+    fn test_check_clean_site() {
+        let temp_dir = setup();
+        let diagnostics = check_site(temp_dir.path(), &CheckOptions::default()).expect("Check failed");
+        assert!(diagnostics.is_empty(), "{}", diagnostics);
+        assert!(!temp_dir.path().join("public").exists());
+    }
+
+    #[test]
+    fn test_check_reports_problems() {
+        let temp_dir = setup();
+        let project_dir = temp_dir.path();
+        fs::write(project_dir.join("content/posts/b.md"), "---\ntitle: B\ndate: 2023-01-02\nslug: a\n---\nB\n").unwrap();
+        fs::write(project_dir.join("content/posts/c.md"), "---\ntitle: C\ndate: 2023-01-03\nslug: c\ntemplate: missing\n---\nC\n").unwrap();
+        fs::write(project_dir.join("content/posts/d.md"), "---\ntitle: D\ndate: 2023-01-04\nslug: d\n---\n[Gone](/posts/gone/)\n").unwrap();
+        fs::write(project_dir.join("themes/default/post.html"), "{{ post.meta.title }}{{ post.content | safe }}{{ post.meta.subtitle }}").unwrap();
+
+        let diagnostics = check_site(project_dir, &CheckOptions::default()).expect("Check failed");
+        let messages: Vec<String> = diagnostics
+            .iter()
+            .map(|d| format!("{}: {}", d.file.file_name().unwrap().to_string_lossy(), d.message))
+            .collect();
+        assert_eq!(diagnostics.error_count(), 4, "{:#?}", messages);
+        assert!(messages[0].starts_with("a.md: Failed to render template post.html"), "{:#?}", messages);
+        assert!(messages[0].contains("post.meta.subtitle"), "{:#?}", messages);
+        assert!(messages[1].starts_with("b.md: Slug `a` is already used by"), "{:#?}", messages);
+        assert!(messages[2].starts_with("c.md: Post `c` (C) requests template `missing.html`"), "{:#?}", messages);
+        assert!(messages[3].starts_with("d.md: Failed to render template post.html"), "{:#?}", messages);
+        assert!(!project_dir.join("public").exists());
+
+        fs::write(project_dir.join("themes/default/post.html"), "{{ post.meta.title }}{{ post.content | safe }}").unwrap();
+        let diagnostics = check_site(project_dir, &CheckOptions::default()).expect("Check failed");
+        let link = diagnostics.iter().find(|d| d.file.ends_with("d.md")).expect("Missing link diagnostic");
+        assert_eq!(link.severity, Severity::Error);
+        assert_eq!(link.message, "Link to `/posts/gone/` on /posts/d/index.html does not match any page or file");
+
+        let json: serde_json::Value = serde_json::from_str(&diagnostics.to_json().unwrap()).unwrap();
+        assert_eq!(json["errors"], 4, "{}", diagnostics);
+        assert_eq!(json["diagnostics"][0]["severity"], "error");
+    }
+
+    #[test]
+    fn test_check_reports_config_errors() {
+        let temp_dir = setup();
+        fs::write(temp_dir.path().join("config.toml"), "title = \n").unwrap();
+        let diagnostics = check_site(temp_dir.path(), &CheckOptions::default()).expect("Check failed");
+        assert_eq!(diagnostics.error_count(), 1);
+        assert!(diagnostics.iter().next().unwrap().file.ends_with("config.toml"));
+
+        fs::write(temp_dir.path().join("config.toml"), "title = \"Test\"\nbase_url = \"https://example.com\"\n").unwrap();
+        fs::create_dir_all(temp_dir.path().join("data")).unwrap();
+        fs::write(temp_dir.path().join("data/talks.json"), "[\n  {\"title\": \"Async\",}\n]").unwrap();
+        let diagnostics = check_site(temp_dir.path(), &CheckOptions::default()).expect("Check failed");
+        let diagnostic = diagnostics.iter().next().unwrap();
+        assert!(diagnostic.file.ends_with("data/talks.json"));
+        assert_eq!((diagnostic.line, diagnostic.column), (Some(2), Some(21)), "{}", diagnostic);
+    }
+}
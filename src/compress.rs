@@ -0,0 +1,132 @@
+use crate::domain::CompressConfig;
+use crate::error::{IoResultExt, Result};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use rayon::prelude::*;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use walkdir::WalkDir;
+
+const COMPRESSIBLE_EXTENSIONS: &[&str] = &["html", "htm", "css", "js", "mjs", "xml", "json", "svg", "txt", "map"];
+const DEFAULT_MIN_SIZE: u64 = 1024;
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CompressSummary {
+    pub files: usize,
+    pub gzip_files: usize,
+    pub brotli_files: usize,
+}
+
+pub fn compress_output(output_dir: &Path, config: &CompressConfig) -> Result<CompressSummary> {
+    let gzip = config.gzip.unwrap_or(false);
+    let brotli = config.brotli.unwrap_or(false);
+    if !gzip && !brotli {
+        return Ok(CompressSummary::default());
+    }
+    let min_size = config.min_size.unwrap_or(DEFAULT_MIN_SIZE);
+
+    let files: Vec<_> = WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter(|e| {
+            e.path()
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| COMPRESSIBLE_EXTENSIONS.contains(&ext))
+        })
+        .filter(|e| e.metadata().is_ok_and(|m| m.len() >= min_size))
+        .map(|e| e.into_path())
+        .collect();
+
+    let written = files
+        .par_iter()
+        .map(|path| -> Result<(bool, bool)> {
+            let bytes = fs::read(path).io("read", path)?;
+            let gzipped = gzip && write_if_smaller(path, "gz", &bytes, gzip_bytes(&bytes).io("gzip", path)?)?;
+            let brotlied = brotli && write_if_smaller(path, "br", &bytes, brotli_bytes(&bytes).io("brotli-compress", path)?)?;
+            Ok((gzipped, brotlied))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(CompressSummary {
+        files: files.len(),
+        gzip_files: written.iter().filter(|(gz, _)| *gz).count(),
+        brotli_files: written.iter().filter(|(_, br)| *br).count(),
+    })
+}
+
+fn write_if_smaller(path: &Path, suffix: &str, original: &[u8], compressed: Vec<u8>) -> Result<bool> {
+    if compressed.len() >= original.len() {
+        return Ok(false);
+    }
+    let mut target = path.as_os_str().to_owned();
+    target.push(".");
+    target.push(suffix);
+    fs::write(&target, compressed).io("write", &target)?;
+    Ok(true)
+}
+
+fn gzip_bytes(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(bytes)?;
+    encoder.finish()
+}
+
+fn brotli_bytes(bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut output = Vec::new();
+    {
+        let mut encoder = brotli::CompressorWriter::new(&mut output, 4096, 11, 22);
+        encoder.write_all(bytes)?;
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compress_output() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let output = temp_dir.path();
+        let page = "<p>Hello, compression!</p>\n".repeat(100);
+        fs::create_dir_all(output.join("posts/a")).unwrap();
+        fs::write(output.join("posts/a/index.html"), &page).unwrap();
+        fs::write(output.join("small.css"), "a{color:red}").unwrap();
+        fs::write(output.join("photo.png"), page.as_bytes()).unwrap();
+
+        let config = CompressConfig {
+            gzip: Some(true),
+            brotli: Some(true),
+            ..Default::default()
+        };
+        let summary = compress_output(output, &config).unwrap();
+        assert_eq!(
+            summary,
+            CompressSummary {
+                files: 1,
+                gzip_files: 1,
+                brotli_files: 1,
+            }
+        );
+
+        let mut gunzipped = String::new();
+        flate2::read::GzDecoder::new(fs::File::open(output.join("posts/a/index.html.gz")).unwrap())
+            .read_to_string(&mut gunzipped)
+            .unwrap();
+        assert_eq!(gunzipped, page);
+
+        let mut unbrotlied = String::new();
+        brotli::Decompressor::new(fs::File::open(output.join("posts/a/index.html.br")).unwrap(), 4096)
+            .read_to_string(&mut unbrotlied)
+            .unwrap();
+        assert_eq!(unbrotlied, page);
+
+        assert!(!output.join("small.css.gz").exists());
+        assert!(!output.join("photo.png.gz").exists());
+    }
+}
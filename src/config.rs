@@ -1,11 +1,15 @@
 use crate::domain::Config;
-use anyhow::{Context, Result};
+use crate::error::{BlogError, IoResultExt, Result};
 use std::fs;
 use std::path::Path;
 
 pub fn load_config<P: AsRef<Path>>(path: P) -> Result<Config> {
-    let content = fs::read_to_string(path).context("Failed to read config file")?;
-    let config: Config = toml::from_str(&content).context("Failed to parse config file")?;
+    let path = path.as_ref();
+    let content = fs::read_to_string(path).io("read config file", path)?;
+    let config: Config = toml::from_str(&content).map_err(|source| BlogError::Config {
+        path: path.to_path_buf(),
+        source,
+    })?;
     Ok(config)
 }
 
@@ -1,30 +1,266 @@
-use crate::domain::Post;
-use crate::parser::parse_markdown;
-use anyhow::{Context, Result};
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::domain::{Config, Post, PostMeta, Resource};
+use crate::html::{resolve_local_url, rewrite_tags};
+use crate::error::{BlogError, IoResultExt, Result};
+use crate::i18n::{is_bundle_index, language_of};
+use crate::parser::{parse_frontmatter, parse_translation, permalink, CrossReferences};
+use chrono::{DateTime, NaiveDate};
 use rayon::prelude::*;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
 pub fn load_posts<P: AsRef<Path>>(dir_path: P) -> Result<Vec<Post>> {
+    let (posts, diagnostics) = load_posts_with_diagnostics(dir_path)?;
+    if diagnostics.has_errors() {
+        return Err(BlogError::Content(diagnostics));
+    }
+    diagnostics.log_warnings();
+    Ok(posts)
+}
+
+pub fn load_posts_with_diagnostics<P: AsRef<Path>>(dir_path: P) -> Result<(Vec<Post>, Diagnostics)> {
+    load_posts_with_config(dir_path, &Config::default())
+}
+
+pub fn load_posts_with_config<P: AsRef<Path>>(dir_path: P, config: &Config) -> Result<(Vec<Post>, Diagnostics)> {
+    let dir_path = dir_path.as_ref();
     let entries: Vec<_> = WalkDir::new(dir_path)
         .into_iter()
         .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file() && e.path().extension().map_or(false, |ext| ext == "md"))
+        .filter(|e| e.file_type().is_file())
         .collect();
 
-    entries
+    let bundles: HashSet<PathBuf> = entries
+        .iter()
+        .filter(|e| is_bundle_index(&e.file_name().to_string_lossy(), config))
+        .filter_map(|e| e.path().parent())
+        .filter(|dir| *dir != dir_path)
+        .map(Path::to_path_buf)
+        .collect();
+
+    let mut resources: Vec<(PathBuf, PathBuf, u64)> = Vec::new();
+    let mut sources = Vec::new();
+    for entry in &entries {
+        let path = entry.path();
+        let is_markdown = path.extension().is_some_and(|ext| ext == "md");
+        let is_index = is_bundle_index(&entry.file_name().to_string_lossy(), config) && path.parent().is_some_and(|dir| bundles.contains(dir));
+        match nearest_bundle(dir_path, path, &bundles) {
+            Some(bundle) if !is_index => {
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                resources.push((bundle.to_path_buf(), path.strip_prefix(bundle).unwrap_or(path).to_path_buf(), size));
+            }
+            _ if is_markdown => sources.push(path.to_path_buf()),
+            _ => {}
+        }
+    }
+
+    sources.sort();
+    let documents: Vec<(PathBuf, Result<Document>)> = sources
         .into_par_iter()
-        .map(|entry| {
-            let content = fs::read_to_string(entry.path())
-                .with_context(|| format!("Failed to read file: {:?}", entry.path()))?;
-            
-            let post = parse_markdown(&content)
-                .with_context(|| format!("Failed to parse file: {:?}", entry.path()))?;
-            
-            Ok(post)
+        .map(|path| {
+            let document = fs::read_to_string(&path).io("read", &path).and_then(|content| {
+                let meta = parse_frontmatter(&content).map_err(|e| with_path(e, &path))?;
+                let lang = language_of(&path, &meta, config);
+                Ok(Document { content, meta, lang })
+            });
+            (path, document)
         })
-        .collect()
+        .collect();
+
+    let mut references = CrossReferences::default();
+    let mut translated: Vec<_> = documents.iter().filter_map(|(path, document)| Some((path, document.as_ref().ok()?))).collect();
+    translated.sort_by_key(|(_, document)| document.lang != config.default_language());
+    for (path, Document { meta, lang, .. }) in translated {
+        let url = format!("{}{}", config.language_prefix(lang), permalink(&meta.slug));
+        references.insert_translation(&relative_path(dir_path, path), meta, lang, url);
+    }
+
+    let results: Vec<(PathBuf, Result<Post>)> = documents
+        .into_par_iter()
+        .map(|(path, document)| {
+            let post = document.and_then(|Document { content, lang, .. }| {
+                let mut post = parse_translation(&content, &references, &lang).map_err(|e| with_path(e, &path))?;
+                post.url = format!("{}{}", config.language_prefix(&lang), post.url);
+                post.lang = lang;
+                Ok(load_post(dir_path, &path, post, &bundles, &resources, config))
+            });
+            (path, post)
+        })
+        .collect();
+
+    let languages = config.language_codes();
+    let mut posts: Vec<Post> = Vec::new();
+    let mut diagnostics = Diagnostics::default();
+    let mut slugs: HashMap<(String, String), PathBuf> = HashMap::new();
+    for (path, result) in results {
+        match result {
+            Ok(post) => {
+                if !languages.contains(&post.lang.as_str()) {
+                    diagnostics.push(Diagnostic::error(&path, format!("Language `{}` is not declared in config.toml", post.lang)));
+                    continue;
+                }
+                let key = (post.lang.clone(), post.meta.slug.clone());
+                if let Some(first) = slugs.get(&key) {
+                    diagnostics.push(Diagnostic::error(
+                        &path,
+                        format!("Slug `{}` is already used by {}", post.meta.slug, first.display()),
+                    ));
+                    continue;
+                }
+                slugs.insert(key, path.clone());
+                lint_post(&path, &post, &mut diagnostics);
+                posts.push(post);
+            }
+            Err(e) => {
+                let diagnostic = Diagnostic::error(&path, e.report());
+                diagnostics.push(match e {
+                    BlogError::Frontmatter { line, column, .. } | BlogError::BrokenReference { line, column, .. } => diagnostic.at(line, column),
+                    _ => diagnostic,
+                });
+            }
+        }
+    }
+    diagnostics.sort();
+
+    Ok((posts, diagnostics))
+}
+
+struct Document {
+    content: String,
+    meta: PostMeta,
+    lang: String,
+}
+
+fn load_post(
+    content_dir: &Path,
+    path: &Path,
+    mut post: Post,
+    bundles: &HashSet<PathBuf>,
+    resources: &[(PathBuf, PathBuf, u64)],
+    config: &Config,
+) -> Post {
+    let is_index = path.file_name().is_some_and(|n| is_bundle_index(&n.to_string_lossy(), config));
+    let bundle_dir = path.parent().filter(|dir| bundles.contains(*dir) && is_index);
+    match bundle_dir {
+        Some(bundle_dir) => {
+            post.section = section_of(content_dir, bundle_dir);
+            post.resources = resources
+                .iter()
+                .filter(|(bundle, _, _)| bundle == bundle_dir)
+                .map(|(_, relative, size)| resource(&post.url, relative, *size))
+                .collect();
+            post.resources.sort_by(|a, b| a.name.cmp(&b.name));
+            resolve_resource_links(&mut post);
+            post.bundle_dir = Some(bundle_dir.to_path_buf());
+        }
+        None => post.section = section_of(content_dir, path),
+    }
+    post.source_path = Some(path.to_path_buf());
+    post
+}
+
+fn with_path(error: BlogError, path: &Path) -> BlogError {
+    match error {
+        BlogError::Frontmatter { line, column, message, .. } => BlogError::Frontmatter {
+            path: path.to_path_buf(),
+            line,
+            column,
+            message,
+        },
+        BlogError::BrokenReference { line, column, target, .. } => BlogError::BrokenReference {
+            path: path.to_path_buf(),
+            line,
+            column,
+            target,
+        },
+        e => e,
+    }
+}
+
+fn relative_path(content_dir: &Path, path: &Path) -> String {
+    path.strip_prefix(content_dir)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn lint_post(path: &Path, post: &Post, diagnostics: &mut Diagnostics) {
+    if post.meta.title.trim().is_empty() {
+        diagnostics.push(Diagnostic::warning(path, "Post has an empty title"));
+    }
+    let date = post.meta.date.trim();
+    let valid_date = NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() || DateTime::parse_from_rfc3339(date).is_ok();
+    if !valid_date {
+        diagnostics.push(Diagnostic::warning(
+            path,
+            format!("Date `{}` is not YYYY-MM-DD or RFC 3339, so the post may be sorted incorrectly", post.meta.date),
+        ));
+    }
+}
+
+fn nearest_bundle<'a>(content_dir: &Path, path: &Path, bundles: &'a HashSet<PathBuf>) -> Option<&'a Path> {
+    path.ancestors()
+        .skip(1)
+        .take_while(|dir| *dir != content_dir)
+        .find_map(|dir| bundles.get(dir))
+        .map(PathBuf::as_path)
+}
+
+fn resource(post_url: &str, relative: &Path, size: u64) -> Resource {
+    let name = relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    let kind = match relative.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("png" | "jpg" | "jpeg" | "gif" | "webp" | "avif" | "svg") => "image",
+        Some("mp4" | "webm" | "mov") => "video",
+        Some("mp3" | "ogg" | "wav") => "audio",
+        _ => "file",
+    };
+    Resource {
+        url: format!("{}{}", post_url, name),
+        name,
+        kind: kind.to_string(),
+        size,
+    }
+}
+
+fn resolve_resource_links(post: &mut Post) {
+    let resolve = |url: &str| -> Option<String> {
+        if url.starts_with('/') || url.starts_with('#') {
+            return None;
+        }
+        let local = resolve_local_url(url, "", "")?;
+        let resource = post.resources.iter().find(|r| r.name == local.path)?;
+        Some(format!("{}{}{}", resource.url, local.query, local.fragment))
+    };
+
+    if let Some(image) = post.meta.image.as_deref().and_then(resolve) {
+        post.meta.image = Some(image);
+    }
+    post.content = rewrite_tags(&post.content, |tag| {
+        for (key, value) in tag.attrs.iter_mut() {
+            if !matches!(key.to_ascii_lowercase().as_str(), "href" | "src" | "poster") {
+                continue;
+            }
+            if let Some(resolved) = value.as_deref().and_then(resolve) {
+                *value = Some(resolved);
+            }
+        }
+    });
+}
+
+fn section_of(content_dir: &Path, path: &Path) -> Option<String> {
+    let relative = path.strip_prefix(content_dir).ok()?;
+    let mut components = relative.components();
+    let first = components.next()?;
+    components.next()?;
+    Some(first.as_os_str().to_string_lossy().into_owned())
 }
 
 #[cfg(test)]
@@ -66,4 +302,144 @@ slug: post-2
         assert!(titles.contains(&"Post 1".to_string()));
         assert!(titles.contains(&"Post 2".to_string()));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_load_posts_assigns_sections() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let content_dir = temp_dir.path().join("content");
+        fs::create_dir_all(content_dir.join("photos/2023")).expect("Failed to create section dir");
+
+        let post = |slug: &str| format!("---\ntitle: {}\ndate: 2023-01-01\nslug: {}\n---\nBody\n", slug, slug);
+        fs::write(content_dir.join("about.md"), post("about")).unwrap();
+        fs::write(content_dir.join("photos/2023/trip.md"), post("trip")).unwrap();
+
+        let posts = load_posts(&content_dir).expect("Failed to load posts");
+        let section = |slug: &str| posts.iter().find(|p| p.meta.slug == slug).unwrap().section.clone();
+
+        assert_eq!(section("about"), None);
+        assert_eq!(section("trip"), Some("photos".to_string()));
+    }
+
+    #[test]
+    fn test_load_page_bundles() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let content_dir = temp_dir.path().join("content");
+        let bundle = content_dir.join("posts/my-post");
+        fs::create_dir_all(bundle.join("data")).expect("Failed to create bundle dir");
+
+        let index = "---\ntitle: My Post\ndate: 2023-01-01\nslug: my-post\nimage: cover.png\n---\n![Diagram](./diagram.png)\n\n[Data](data/table.csv#rows) [Home](/) [Other](other.png)\n";
+        fs::write(bundle.join("index.md"), index).unwrap();
+        fs::write(bundle.join("notes.md"), "not a post").unwrap();
+        fs::write(bundle.join("diagram.png"), "png").unwrap();
+        fs::write(bundle.join("cover.png"), "png").unwrap();
+        fs::write(bundle.join("data/table.csv"), "a,b").unwrap();
+        fs::write(content_dir.join("posts/plain.md"), "---\ntitle: Plain\ndate: 2023-01-02\nslug: plain\n---\nBody\n").unwrap();
+
+        let posts = load_posts(&content_dir).expect("Failed to load posts");
+        assert_eq!(posts.len(), 2);
+
+        let post = posts.iter().find(|p| p.meta.slug == "my-post").unwrap();
+        assert_eq!(post.section, Some("posts".to_string()));
+        assert_eq!(post.bundle_dir, Some(bundle.clone()));
+        assert_eq!(
+            post.resources.iter().map(|r| (r.name.as_str(), r.kind.as_str())).collect::<Vec<_>>(),
+            vec![("cover.png", "image"), ("data/table.csv", "file"), ("diagram.png", "image"), ("notes.md", "file")]
+        );
+        assert_eq!(post.resources[1].url, "/posts/my-post/data/table.csv");
+        assert_eq!(post.resources[1].size, 3);
+        assert_eq!(post.meta.image, Some("/posts/my-post/cover.png".to_string()));
+        assert!(post.content.contains(r#"<img src="/posts/my-post/diagram.png" alt="Diagram" />"#), "{}", post.content);
+        assert!(post.content.contains(r#"href="/posts/my-post/data/table.csv#rows""#));
+        assert!(post.content.contains(r#"href="/""#));
+        assert!(post.content.contains(r#"href="other.png""#));
+
+        let plain = posts.iter().find(|p| p.meta.slug == "plain").unwrap();
+        assert!(plain.resources.is_empty());
+        assert_eq!(plain.bundle_dir, None);
+    }
+
+    #[test]
+    fn test_load_posts_collects_diagnostics() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let content_dir = temp_dir.path().join("content");
+        fs::create_dir_all(&content_dir).unwrap();
+
+        fs::write(content_dir.join("a.md"), "---\ntitle: A\ndate: 2023-01-01\nslug: same\n---\nA\n").unwrap();
+        fs::write(content_dir.join("b.md"), "---\ntitle: B\ndate: 2023-01-02\nslug: same\n---\nB\n").unwrap();
+        fs::write(content_dir.join("c.md"), "---\ntitle: C\ndate: 2023-01-03\ndraft: maybe\nslug: c\n---\nC\n").unwrap();
+        fs::write(content_dir.join("d.md"), "no frontmatter").unwrap();
+        fs::write(content_dir.join("e.md"), "---\ntitle: \"\"\ndate: last tuesday\nslug: e\n---\nE\n").unwrap();
+
+        let (posts, diagnostics) = load_posts_with_diagnostics(&content_dir).expect("Failed to load posts");
+
+        let mut slugs: Vec<&str> = posts.iter().map(|p| p.meta.slug.as_str()).collect();
+        slugs.sort();
+        assert_eq!(slugs, vec!["e", "same"]);
+        assert_eq!(diagnostics.error_count(), 3);
+        assert_eq!(diagnostics.warning_count(), 2);
+
+        let lines: Vec<String> = diagnostics
+            .iter()
+            .map(|d| d.to_string().replace(&content_dir.display().to_string(), "content"))
+            .collect();
+        assert!(lines[0].starts_with("error: content/b.md: Slug `same` is already used by"), "{:?}", lines);
+        assert!(lines[1].starts_with("error: content/c.md:4:8: Invalid frontmatter: draft: invalid type"), "{:?}", lines);
+        assert_eq!(lines[2], "error: content/d.md:1: No frontmatter found");
+        assert_eq!(lines[3], "warning: content/e.md: Post has an empty title");
+        assert!(lines[4].starts_with("warning: content/e.md: Date `last tuesday`"), "{:?}", lines);
+        assert_eq!(diagnostics.summary(), "3 errors, 2 warnings in 4 files");
+
+        let err = load_posts(&content_dir).unwrap_err();
+        assert!(err.to_string().ends_with("3 errors, 2 warnings in 4 files"));
+    }
+
+    #[test]
+    fn test_load_posts_resolves_cross_references() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let content_dir = temp_dir.path().join("content");
+        fs::create_dir_all(content_dir.join("posts/bundle")).unwrap();
+
+        fs::write(content_dir.join("posts/a.md"), "---\ntitle: A\ndate: 2023-01-01\nslug: renamed-a\n---\nSee [[b]].\n").unwrap();
+        fs::write(content_dir.join("posts/bundle/index.md"), "---\ntitle: B\ndate: 2023-01-02\nslug: b\n---\nBack to [A](@/posts/a.md).\n").unwrap();
+
+        let posts = load_posts(&content_dir).expect("Failed to load posts");
+        let content = |slug: &str| posts.iter().find(|p| p.meta.slug == slug).unwrap().content.clone();
+        assert_eq!(content("renamed-a"), "<p>See <a href=\"/posts/b/\">B</a>.</p>\n");
+        assert_eq!(content("b"), "<p>Back to <a href=\"/posts/renamed-a/\">A</a>.</p>\n");
+
+        fs::write(content_dir.join("posts/c.md"), "---\ntitle: C\ndate: 2023-01-03\nslug: c\n---\nSee [[a]].\n").unwrap();
+        let (_, diagnostics) = load_posts_with_diagnostics(&content_dir).expect("Failed to load posts");
+        let lines: Vec<String> = diagnostics
+            .iter()
+            .map(|d| d.to_string().replace(&content_dir.display().to_string(), "content"))
+            .collect();
+        assert_eq!(lines, vec!["error: content/posts/c.md:6:5: Cross-reference `[[a]]` does not match any post"]);
+    }
+
+    #[test]
+    fn test_load_posts_with_languages() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let content_dir = temp_dir.path().join("content");
+        fs::create_dir_all(content_dir.join("posts/trip")).unwrap();
+        let mut config = Config::default();
+        config.languages.insert("es".to_string(), Default::default());
+
+        fs::write(content_dir.join("posts/trip/index.md"), "---\ntitle: Trip\ndate: 2023-01-01\nslug: trip\n---\n![Map](map.png)\n").unwrap();
+        fs::write(content_dir.join("posts/trip/index.es.md"), "---\ntitle: Viaje\ndate: 2023-01-01\nslug: viaje\n---\n![Mapa](map.png) [[trip]]\n").unwrap();
+        fs::write(content_dir.join("posts/trip/map.png"), "png").unwrap();
+        fs::write(content_dir.join("posts/nota.md"), "---\ntitle: Nota\ndate: 2023-01-02\nslug: trip\nlang: es\n---\nNota\n").unwrap();
+        fs::write(content_dir.join("posts/note.fr.md"), "---\ntitle: Note\ndate: 2023-01-03\nslug: note\nlang: fr\n---\nNote\n").unwrap();
+
+        let (posts, diagnostics) = load_posts_with_config(&content_dir, &config).expect("Failed to load posts");
+        let viaje = posts.iter().find(|p| p.meta.slug == "viaje").unwrap();
+        assert_eq!((viaje.lang.as_str(), viaje.url.as_str()), ("es", "/es/posts/viaje/"));
+        assert_eq!(viaje.resources[0].url, "/es/posts/viaje/map.png");
+        assert!(viaje.content.contains("<a href=\"/es/posts/trip/\">Nota</a>"), "{}", viaje.content);
+        let nota = posts.iter().find(|p| p.meta.title == "Nota").unwrap();
+        assert_eq!(nota.url, "/es/posts/trip/");
+        assert_eq!(posts.iter().find(|p| p.meta.title == "Trip").unwrap().url, "/posts/trip/");
+
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, vec!["Language `fr` is not declared in config.toml"]);
+    }
+}
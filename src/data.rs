@@ -0,0 +1,197 @@
+use crate::error::{BlogError, IoResultExt, Result};
+use crate::parser::{location, strip_locations};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+pub const DATA_DIR: &str = "data";
+
+pub fn load_data(project_dir: &Path) -> Result<Value> {
+    let data_dir = project_dir.join(DATA_DIR);
+    let mut data = Map::new();
+    if !data_dir.is_dir() {
+        return Ok(Value::Object(data));
+    }
+
+    let mut entries: Vec<_> = WalkDir::new(&data_dir)
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        let Some(extension) = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase) else {
+            continue;
+        };
+        if !matches!(extension.as_str(), "toml" | "yaml" | "yml" | "json" | "csv") {
+            continue;
+        }
+        let content = fs::read_to_string(&path).io("read", &path)?;
+        let value = parse_data(&content, &extension).map_err(|(line, column, message)| BlogError::Data {
+            path: path.clone(),
+            line,
+            column,
+            message,
+        })?;
+
+        let relative = path.strip_prefix(&data_dir).unwrap_or(&path).with_extension("");
+        let keys: Vec<String> = relative.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+        insert(&mut data, &keys, value).map_err(|key| {
+            BlogError::InvalidConfig(format!("Data file {:?} conflicts with another definition of `data.{}`", path, key))
+        })?;
+    }
+    Ok(Value::Object(data))
+}
+
+fn insert(data: &mut Map<String, Value>, keys: &[String], value: Value) -> std::result::Result<(), String> {
+    let (last, parents) = keys.split_last().expect("data files have a name");
+    let mut node = data;
+    for key in parents {
+        node = match node.entry(key.clone()).or_insert_with(|| Value::Object(Map::new())) {
+            Value::Object(map) => map,
+            _ => return Err(key.clone()),
+        };
+    }
+    if node.contains_key(last) {
+        return Err(keys.join("."));
+    }
+    node.insert(last.clone(), value);
+    Ok(())
+}
+
+type ParseError = (usize, Option<usize>, String);
+
+fn parse_data(content: &str, extension: &str) -> std::result::Result<Value, ParseError> {
+    match extension {
+        "toml" => toml::from_str(content).map_err(|e| {
+            let (line, column) = e.span().map_or((1, None), |span| location(content, span.start));
+            (line, column, e.message().to_string())
+        }),
+        "yaml" | "yml" => serde_yaml::from_str(content).map_err(|e| {
+            let location = e.location();
+            (location.as_ref().map_or(1, |l| l.line()), location.map(|l| l.column()), strip_locations(&e.to_string()))
+        }),
+        "json" => serde_json::from_str(content).map_err(|e| (e.line(), Some(e.column()), strip_locations(&e.to_string()))),
+        _ => parse_csv(content),
+    }
+}
+
+fn parse_csv(content: &str) -> std::result::Result<Value, ParseError> {
+    let mut rows: Vec<(usize, Vec<String>)> = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut line = 1;
+    let mut row_line = 1;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, quoted) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            ('"', true) => quoted = false,
+            ('"', false) if field.is_empty() => quoted = true,
+            (',', false) => row.push(std::mem::take(&mut field)),
+            ('\r', false) if chars.peek() == Some(&'\n') => {}
+            ('\n', false) => {
+                row.push(std::mem::take(&mut field));
+                if row.iter().any(|f| !f.is_empty()) {
+                    rows.push((row_line, std::mem::take(&mut row)));
+                }
+                row.clear();
+                line += 1;
+                row_line = line;
+            }
+            (c, _) => {
+                if c == '\n' {
+                    line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+    if quoted {
+        return Err((row_line, None, "unterminated quoted field".to_string()));
+    }
+    row.push(field);
+    if row.iter().any(|f| !f.is_empty()) {
+        rows.push((row_line, row));
+    }
+
+    let mut rows = rows.into_iter();
+    let Some((_, header)) = rows.next() else {
+        return Ok(Value::Array(Vec::new()));
+    };
+    rows.map(|(line, fields)| {
+        if fields.len() != header.len() {
+            return Err((line, None, format!("expected {} fields, found {}", header.len(), fields.len())));
+        }
+        Ok(Value::Object(header.iter().cloned().zip(fields.into_iter().map(Value::String)).collect()))
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_data() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let data_dir = temp_dir.path().join(DATA_DIR);
+        fs::create_dir_all(data_dir.join("projects")).unwrap();
+        fs::write(data_dir.join("site.toml"), "[social]\nmastodon = \"@me@example.com\"\n").unwrap();
+        fs::write(data_dir.join("talks.yaml"), "- title: Async Rust\n  year: 2024\n").unwrap();
+        fs::write(data_dir.join("projects/cards.json"), r#"[{"name": "blog-engine", "stars": 42}]"#).unwrap();
+        fs::write(data_dir.join("blogroll.csv"), "name,url\n\"Tips, Tricks\",https://a.example\n\n\"Say \"\"hi\"\"\",https://b.example\n").unwrap();
+        fs::write(data_dir.join("notes.txt"), "ignored").unwrap();
+
+        let data = load_data(temp_dir.path()).expect("Failed to load data");
+        assert_eq!(data["site"]["social"]["mastodon"], "@me@example.com");
+        assert_eq!(data["talks"][0]["year"], 2024);
+        assert_eq!(data["projects"]["cards"][0]["stars"], 42);
+        assert_eq!(
+            data["blogroll"],
+            serde_json::json!([
+                { "name": "Tips, Tricks", "url": "https://a.example" },
+                { "name": "Say \"hi\"", "url": "https://b.example" },
+            ])
+        );
+        assert!(data.get("notes").is_none());
+    }
+
+    #[test]
+    fn test_load_data_errors() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let data_dir = temp_dir.path().join(DATA_DIR);
+        fs::create_dir_all(&data_dir).unwrap();
+
+        for (name, content, expected) in [
+            ("a.toml", "title = \"ok\"\nbroken = \n", (2, Some(10))),
+            ("b.yaml", "items:\n  - one\n - two\n", (3, Some(2))),
+            ("c.json", "{\n  \"a\": 1,\n}", (3, Some(1))),
+            ("d.csv", "a,b\n1,2\n3\n", (3, None)),
+            ("e.csv", "a,b\n\"open,2\n", (2, None)),
+        ] {
+            fs::write(data_dir.join(name), content).unwrap();
+            let Err(BlogError::Data { path, line, column, message }) = load_data(temp_dir.path()) else {
+                panic!("Expected a data error for {}", name);
+            };
+            assert!(path.ends_with(name), "{:?}", path);
+            assert_eq!((line, column), expected, "{}: {}", name, message);
+            assert!(!message.contains(" at line "), "{}", message);
+            fs::remove_file(data_dir.join(name)).unwrap();
+        }
+
+        fs::write(data_dir.join("site.toml"), "a = 1\n").unwrap();
+        fs::write(data_dir.join("site.json"), "{}").unwrap();
+        assert!(matches!(load_data(temp_dir.path()), Err(BlogError::InvalidConfig(_))));
+    }
+}
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 
-#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
 pub struct PostMeta {
     pub title: String,
     pub date: String,
@@ -9,19 +11,280 @@ pub struct PostMeta {
     pub categories: Option<Vec<String>>,
     pub draft: Option<bool>,
     pub image: Option<String>,
+    #[serde(alias = "layout")]
+    pub template: Option<String>,
+    pub series: Option<String>,
+    pub series_order: Option<u32>,
+    pub authors: Option<Vec<String>>,
+    pub lang: Option<String>,
+    pub translation_key: Option<String>,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
 pub struct Post {
     pub meta: PostMeta,
     pub content: String,
+    #[serde(default)]
+    pub url: String,
+    #[serde(default)]
+    pub lang: String,
+    #[serde(default)]
+    pub translations: Vec<Translation>,
+    #[serde(default)]
+    pub section: Option<String>,
+    #[serde(default)]
+    pub resources: Vec<Resource>,
+    #[serde(default)]
+    pub links: Vec<PostLink>,
+    #[serde(default)]
+    pub backlinks: Vec<PostLink>,
+    #[serde(default)]
+    pub related: Vec<PostLink>,
+    #[serde(default)]
+    pub previous: Option<PostLink>,
+    #[serde(default)]
+    pub next: Option<PostLink>,
+    #[serde(default)]
+    pub breadcrumbs: Vec<Breadcrumb>,
+    #[serde(default)]
+    pub series: Option<SeriesInfo>,
+    #[serde(default)]
+    pub authors: Vec<Author>,
+    #[serde(skip)]
+    pub bundle_dir: Option<PathBuf>,
+    #[serde(skip)]
+    pub source_path: Option<PathBuf>,
 }
 
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Resource {
+    pub name: String,
+    pub url: String,
+    pub kind: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct PostLink {
+    pub slug: String,
+    pub title: String,
+    pub url: String,
+}
+
+#[cfg(test)]
+impl Post {
+    /// A post at `/posts/<slug>/` titled after its slug, for tests to adjust as needed.
+    pub(crate) fn fixture(slug: &str) -> Self {
+        Post {
+            meta: PostMeta {
+                title: slug.to_uppercase(),
+                slug: slug.to_string(),
+                ..Default::default()
+            },
+            url: format!("/posts/{}/", slug),
+            ..Default::default()
+        }
+    }
+}
+
+impl PostLink {
+    pub fn of(post: &Post) -> Self {
+        Self {
+            slug: post.meta.slug.clone(),
+            title: post.meta.title.clone(),
+            url: post.url.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Translation {
+    pub lang: String,
+    pub slug: String,
+    pub title: String,
+    pub url: String,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Breadcrumb {
+    pub title: String,
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SeriesInfo {
+    pub title: String,
+    pub slug: String,
+    pub url: String,
+    pub parts: Vec<PostLink>,
+    pub index: usize,
+    pub total: usize,
+    pub previous: Option<PostLink>,
+    pub next: Option<PostLink>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Author {
+    pub id: String,
+    pub name: String,
+    pub url: String,
+    pub bio: Option<String>,
+    pub avatar: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub links: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     pub title: String,
     pub base_url: String,
     pub description: Option<String>,
     pub posts_per_page: Option<usize>,
     pub theme: Option<String>,
+    pub autoescape: Option<bool>,
+    pub graph: Option<bool>,
+    pub default_language: Option<String>,
+    #[serde(default)]
+    pub languages: BTreeMap<String, LanguageConfig>,
+    #[serde(default)]
+    pub sections: BTreeMap<String, SectionConfig>,
+    #[serde(default)]
+    pub authors: BTreeMap<String, AuthorProfile>,
+    #[serde(default)]
+    pub theme_config: toml::Table,
+    #[serde(default)]
+    pub assets: AssetsConfig,
+    #[serde(default)]
+    pub images: ImagesConfig,
+    #[serde(default)]
+    pub minify: MinifyConfig,
+    #[serde(default)]
+    pub compress: CompressConfig,
+    #[serde(default)]
+    pub links: LinksConfig,
+    #[serde(default)]
+    pub related: RelatedConfig,
+    #[serde(default)]
+    pub navigation: NavigationConfig,
+}
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+impl Config {
+    pub fn default_language(&self) -> &str {
+        self.default_language.as_deref().unwrap_or(DEFAULT_LANGUAGE)
+    }
+
+    pub fn language_codes(&self) -> Vec<&str> {
+        let default = self.default_language();
+        std::iter::once(default)
+            .chain(self.languages.keys().map(String::as_str).filter(|lang| *lang != default))
+            .collect()
+    }
+
+    pub fn language_prefix(&self, lang: &str) -> String {
+        match lang.is_empty() || lang == self.default_language() {
+            true => String::new(),
+            false => format!("/{}", lang),
+        }
+    }
+
+    pub fn language(&self, lang: &str) -> Language {
+        let lang = if lang.is_empty() { self.default_language() } else { lang };
+        let localized = self.languages.get(lang);
+        Language {
+            code: lang.to_string(),
+            title: localized.and_then(|l| l.title.clone()).unwrap_or_else(|| self.title.clone()),
+            description: localized.and_then(|l| l.description.clone()).or_else(|| self.description.clone()),
+            url: format!("{}/", self.language_prefix(lang)),
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct LanguageConfig {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub translations: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct Language {
+    pub code: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub url: String,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct SectionConfig {
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct AuthorProfile {
+    pub name: String,
+    pub bio: Option<String>,
+    pub avatar: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub links: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct AssetsConfig {
+    pub minify: Option<bool>,
+    pub fingerprint: Option<bool>,
+    pub integrity: Option<bool>,
+    #[serde(default)]
+    pub bundles: Vec<BundleConfig>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct BundleConfig {
+    pub output: String,
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct ImagesConfig {
+    pub enabled: Option<bool>,
+    pub widths: Option<Vec<u32>>,
+    pub formats: Option<Vec<String>>,
+    pub quality: Option<u8>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct MinifyConfig {
+    pub html: Option<bool>,
+    pub xml: Option<bool>,
+    pub json: Option<bool>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct CompressConfig {
+    pub gzip: Option<bool>,
+    pub brotli: Option<bool>,
+    pub min_size: Option<u64>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct LinksConfig {
+    pub check: Option<bool>,
+    pub fatal: Option<bool>,
+    pub report: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct RelatedConfig {
+    pub count: Option<usize>,
+    pub text: Option<bool>,
+    pub text_weight: Option<f64>,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize, Clone)]
+pub struct NavigationConfig {
+    pub scope: Option<String>,
 }
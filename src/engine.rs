@@ -1,103 +1,373 @@
 use crate::config::load_config;
-use crate::content::load_posts;
+use crate::content::load_posts_with_config;
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::fingerprint::AssetManifest;
+use crate::images::ImageProcessor;
 use crate::renderer::Renderer;
-use anyhow::Result;
+use crate::theme::Theme;
+use crate::domain::{Config, Post};
+use crate::error::{BlogError, IoResultExt, Result};
 use std::fs;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use walkdir::WalkDir;
 use rayon::prelude::*;
 
+#[derive(Debug, Default, Clone)]
+pub struct BuildOptions {
+    pub include_drafts: bool,
+    pub dev: bool,
+    pub keep_builds: usize,
+    pub keep_going: bool,
+}
+
 pub fn build_site<P: AsRef<Path>>(
     project_dir: P,
     output_dir: P,
     include_drafts: bool,
+) -> Result<()> {
+    let options = BuildOptions {
+        include_drafts,
+        ..Default::default()
+    };
+    build_site_with_options(project_dir, output_dir, &options)
+}
+
+pub fn build_site_with_options<P: AsRef<Path>>(
+    project_dir: P,
+    output_dir: P,
+    options: &BuildOptions,
 ) -> Result<()> {
     let project_dir = project_dir.as_ref();
     let output_dir = output_dir.as_ref();
 
     tracing::info!("Building site from {:?} to {:?}", project_dir, output_dir);
 
-    let config = load_config(project_dir.join("config.toml"))?;
-    
+    let Site {
+        config,
+        mut renderer,
+        mut posts,
+        mut diagnostics,
+    } = load_site(project_dir, options.include_drafts)?;
+    if diagnostics.has_errors() && !options.keep_going {
+        diagnostics.sort();
+        return Err(BlogError::BuildFailed {
+            output: output_dir.to_path_buf(),
+            source: Box::new(BlogError::Content(diagnostics)),
+        });
+    }
+
+    let staging = staging_dir(output_dir)?;
+    if staging.exists() {
+        fs::remove_dir_all(&staging).io("remove", &staging)?;
+    }
+    create_dir(&staging)?;
+
+    if let Err(e) = write_site(project_dir, &staging, &config, &mut renderer, &mut posts, options, &mut diagnostics) {
+        if let Err(cleanup) = fs::remove_dir_all(&staging) {
+            tracing::warn!("Failed to remove staging directory {:?}: {}", staging, cleanup);
+        }
+        return Err(BlogError::BuildFailed {
+            output: output_dir.to_path_buf(),
+            source: Box::new(e),
+        });
+    }
+    publish(&staging, output_dir, options.keep_builds)?;
+
+    diagnostics.sort();
+    if diagnostics.has_errors() {
+        return Err(BlogError::Incomplete(diagnostics));
+    }
+    diagnostics.log_warnings();
+
+    tracing::info!("Build completed successfully.");
+    Ok(())
+}
+
+pub(crate) struct Site {
+    pub config: Config,
+    pub renderer: Renderer,
+    pub posts: Vec<Post>,
+    pub diagnostics: Diagnostics,
+}
+
+pub(crate) fn load_site(project_dir: &Path, include_drafts: bool) -> Result<Site> {
+    let mut config = load_config(project_dir.join("config.toml"))?;
+    crate::authors::load_authors(project_dir, &mut config)?;
+
     let theme_name = config.theme.as_deref().unwrap_or("default");
-    let theme_dir = project_dir.join("themes").join(theme_name); 
-    let renderer = Renderer::new(theme_dir)?;
+    let theme = Theme::load(project_dir, theme_name)?;
+    let mut renderer = Renderer::from_theme(&theme, &config)?;
+    renderer.set_data(crate::data::load_data(project_dir)?);
 
-    let mut posts = load_posts(project_dir.join("content"))?;
+    let (mut posts, mut diagnostics) = load_posts_with_config(project_dir.join("content"), &config)?;
 
     if !include_drafts {
         posts.retain(|p| !p.meta.draft.unwrap_or(false));
     }
 
-    if output_dir.exists() {
-        fs::remove_dir_all(output_dir)?;
-    }
-    fs::create_dir_all(output_dir)?;
-
     posts.sort_by(|a, b| b.meta.date.cmp(&a.meta.date));
+    crate::i18n::sort_by_language(&mut posts, &config);
+    crate::authors::resolve_authors(&mut posts, &config, &mut diagnostics);
 
-    let per_page = config.posts_per_page.unwrap_or(10);
-    let paginated_index = crate::pagination::paginate(&posts, per_page);
-
-    for (i, page) in paginated_index.iter().enumerate() {
-        let index_html = renderer.render_paginated_index(page, &config)?;
-        if i == 0 {
-            fs::write(output_dir.join("index.html"), index_html)?;
-        } else {
-            let page_dir = output_dir.join("page").join((i + 1).to_string());
-            fs::create_dir_all(&page_dir)?;
-            fs::write(page_dir.join("index.html"), index_html)?;
+    posts.retain(|post| match renderer.post_template(post, &config) {
+        Ok(_) => true,
+        Err(e) => {
+            diagnostics.push(Diagnostic::error(&post_source(post), e.report()));
+            false
         }
+    });
+    crate::i18n::link_translations(&mut posts, &config);
+    crate::graph::link_posts(&mut posts, &config.base_url);
+    for posts in posts.chunk_by_mut(|a, b| a.lang == b.lang) {
+        crate::related::relate_posts(posts, &config.related);
+        crate::navigation::link_neighbours(posts, &config)?;
+        crate::series::link_series(posts, &config);
     }
 
-    let tags = crate::taxonomy::group_by_tag(&posts);
-    for (tag, tag_posts) in tags {
-        let tag_html = renderer.render_taxonomy(&tag, &tag_posts, &config)?;
-        let tag_dir = output_dir.join("tags").join(tag);
-        fs::create_dir_all(&tag_dir)?;
-        fs::write(tag_dir.join("index.html"), tag_html)?;
+    Ok(Site {
+        config,
+        renderer,
+        posts,
+        diagnostics,
+    })
+}
+
+pub(crate) fn post_source(post: &Post) -> PathBuf {
+    post.source_path.clone().unwrap_or_else(|| PathBuf::from(&post.meta.slug))
+}
+
+pub(crate) fn post_path(post: &Post) -> String {
+    format!("{}index.html", post.url.trim_start_matches('/'))
+}
+
+pub fn staging_dir(output_dir: &Path) -> Result<PathBuf> {
+    sibling_dir(output_dir, "staging")
+}
+
+pub fn builds_dir(output_dir: &Path) -> Result<PathBuf> {
+    sibling_dir(output_dir, "builds")
+}
+
+fn sibling_dir(output_dir: &Path, suffix: &str) -> Result<PathBuf> {
+    let name = output_dir
+        .file_name()
+        .ok_or_else(|| BlogError::InvalidConfig(format!("Output directory {:?} must have a name", output_dir)))?;
+    Ok(output_dir.with_file_name(format!(".{}.{}", name.to_string_lossy(), suffix)))
+}
+
+fn publish(staging: &Path, output_dir: &Path, keep_builds: usize) -> Result<()> {
+    if !output_dir.exists() {
+        fs::rename(staging, output_dir).io("move the new build to", output_dir)?;
+        return Ok(());
     }
 
-    let categories = crate::taxonomy::group_by_category(&posts);
-    for (category, category_posts) in categories {
-        let category_html = renderer.render_taxonomy(&category, &category_posts, &config)?;
-        let category_dir = output_dir.join("categories").join(category);
-        fs::create_dir_all(&category_dir)?;
-        fs::write(category_dir.join("index.html"), category_html)?;
+    let builds = builds_dir(output_dir)?;
+    create_dir(&builds)?;
+    let previous = builds.join(chrono::Local::now().format("%Y%m%dT%H%M%S%.9f").to_string());
+    match exchange(staging, output_dir) {
+        // The new build is live and the staging directory now holds the previous one.
+        Ok(()) => {
+            if let Err(e) = fs::rename(staging, &previous) {
+                tracing::warn!("Failed to move the previous build to {:?}: {}", previous, e);
+            }
+        }
+        // Without an atomic exchange there is a short window in which `output_dir` does not exist.
+        Err(e) if e.kind() == std::io::ErrorKind::Unsupported => {
+            fs::rename(output_dir, &previous).io("move the previous build out of", output_dir)?;
+            if let Err(e) = fs::rename(staging, output_dir) {
+                fs::rename(&previous, output_dir).io("restore the previous build to", output_dir)?;
+                return Err(BlogError::io("move the new build to", output_dir, e));
+            }
+        }
+        Err(e) => return Err(BlogError::io("swap the new build into", output_dir, e)),
     }
 
-    let sitemap_xml = crate::seo::generate_sitemap(&posts, &config)?;
-    fs::write(output_dir.join("sitemap.xml"), sitemap_xml)?;
+    // The new build is already live, so failing to clean up old ones must not fail the build.
+    prune_builds(&builds, keep_builds);
+    Ok(())
+}
 
-    let rss_xml = crate::seo::generate_rss(&posts, &config)?;
-    fs::write(output_dir.join("rss.xml"), rss_xml)?;
+fn prune_builds(builds: &Path, keep_builds: usize) {
+    let mut retained: Vec<PathBuf> = match fs::read_dir(builds) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).collect(),
+        Err(e) => {
+            tracing::warn!("Failed to read {:?}: {}", builds, e);
+            return;
+        }
+    };
+    retained.sort();
+    let stale = retained.len().saturating_sub(keep_builds);
+    for old in &retained[..stale] {
+        if let Err(e) = fs::remove_dir_all(old) {
+            tracing::warn!("Failed to remove old build {:?}: {}", old, e);
+        }
+    }
+    if keep_builds == 0 {
+        if let Err(e) = fs::remove_dir(builds) {
+            tracing::warn!("Failed to remove {:?}: {}", builds, e);
+        }
+    }
+}
+
+/// Atomically swaps two directories, so readers of `b` always see either the old or the new tree.
+#[cfg(target_os = "linux")]
+fn exchange(a: &Path, b: &Path) -> std::io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
 
-    let search_json = crate::seo::generate_search_index(&posts)?;
-    fs::write(output_dir.join("search.json"), search_json)?;
+    let a = CString::new(a.as_os_str().as_bytes())?;
+    let b = CString::new(b.as_os_str().as_bytes())?;
+    // SAFETY: both paths are valid NUL-terminated strings that outlive the call.
+    let result = unsafe { libc::renameat2(libc::AT_FDCWD, a.as_ptr(), libc::AT_FDCWD, b.as_ptr(), libc::RENAME_EXCHANGE) };
+    if result == 0 {
+        return Ok(());
+    }
+    let error = std::io::Error::last_os_error();
+    match error.raw_os_error() {
+        Some(libc::EINVAL | libc::ENOSYS | libc::EOPNOTSUPP) => Err(std::io::ErrorKind::Unsupported.into()),
+        _ => Err(error),
+    }
+}
 
+#[cfg(not(target_os = "linux"))]
+fn exchange(_a: &Path, _b: &Path) -> std::io::Result<()> {
+    Err(std::io::ErrorKind::Unsupported.into())
+}
+
+fn write_site(
+    project_dir: &Path,
+    output_dir: &Path,
+    config: &Config,
+    renderer: &mut Renderer,
+    posts: &mut [Post],
+    options: &BuildOptions,
+    diagnostics: &mut Diagnostics,
+) -> Result<()> {
     let static_dir = project_dir.join("static");
     if static_dir.exists() {
         copy_recursive(&static_dir, output_dir)?;
     }
 
-    posts.par_iter().try_for_each(|post| -> Result<()> {
-        let post_html = renderer.render_post(post, &config)?;
-        let post_slug = &post.meta.slug;
-        let post_dir = output_dir.join("posts").join(post_slug);
-        fs::create_dir_all(&post_dir)?;
-        fs::write(post_dir.join("index.html"), post_html)?;
+    for post in posts.iter() {
+        let Some(bundle_dir) = &post.bundle_dir else {
+            continue;
+        };
+        let post_dir = output_dir.join(post.url.trim_matches('/'));
+        for resource in &post.resources {
+            let target = post_dir.join(&resource.name);
+            if let Some(parent) = target.parent() {
+                create_dir(parent)?;
+            }
+            let source = bundle_dir.join(&resource.name);
+            fs::copy(&source, &target).io("copy bundle resource", &source)?;
+        }
+    }
+
+    let images = Arc::new(ImageProcessor::new(output_dir, &project_dir.join(".cache/images"), &config.images)?);
+    posts.par_iter_mut().try_for_each(|post| -> Result<()> {
+        let base_dir = post.url.trim_matches('/').to_string();
+        post.content = images.rewrite_html(&post.content, &base_dir, &config.base_url)?;
+        if let Some(image) = &post.meta.image {
+            if let Some(local) = crate::html::resolve_local_url(image, &base_dir, &config.base_url) {
+                images.process(&local.path)?;
+            }
+        }
         Ok(())
     })?;
 
-    tracing::info!("Build completed successfully.");
+    let mut asset_urls = crate::assets::process_assets(&project_dir.join("assets"), output_dir, &config.assets, options.dev)?;
+    let fingerprint = config.assets.fingerprint.unwrap_or(false);
+    let mut manifest = if fingerprint {
+        let manifest = crate::fingerprint::fingerprint_assets(output_dir)?;
+        for url in asset_urls.values_mut() {
+            if let Some(hashed) = manifest.url(url) {
+                *url = hashed;
+            }
+        }
+        images.set_fingerprinted(manifest.files.clone());
+        manifest
+    } else {
+        AssetManifest::default()
+    };
+    renderer.set_assets(asset_urls);
+    renderer.register_images(Arc::clone(&images));
+    renderer.register_site(posts, config, vec![output_dir.to_path_buf()], manifest.files.clone());
+
+    let pages = render_pages(renderer, posts, config, diagnostics)?;
+    if diagnostics.has_errors() && !options.keep_going {
+        diagnostics.sort();
+        return Err(BlogError::Content(std::mem::take(diagnostics)));
+    }
+    pages.par_iter().try_for_each(|(path, contents)| {
+        let target = output_dir.join(path);
+        if let Some(parent) = target.parent() {
+            create_dir(parent)?;
+        }
+        write_file(target, contents)
+    })?;
+
+    if fingerprint {
+        // Images first requested by templates through `image()` were generated while rendering.
+        crate::fingerprint::fingerprint_files(output_dir, images.take_generated(), &mut manifest)?;
+        write_file(output_dir.join("assets-manifest.json"), manifest.to_json()?)?;
+    }
+
+    let integrity = config.assets.integrity.unwrap_or(true);
+    crate::fingerprint::rewrite_html_references(output_dir, &manifest, &config.base_url, integrity)?;
+
+    if config.links.check.unwrap_or(true) {
+        let report = crate::links::check_output(output_dir, &config.base_url)?;
+        if !report.external.is_empty() {
+            tracing::info!("Found {} external links (not checked)", report.external.len());
+        }
+        if let Some(path) = &config.links.report {
+            // Only rewrite the report when its content changed.
+            let path = project_dir.join(path);
+            let json = report.to_json()?;
+            if fs::read_to_string(&path).ok().as_deref() != Some(json.as_str()) {
+                write_file(path, json)?;
+            }
+        }
+        diagnostics.extend(report.diagnostics(posts, config.links.fatal.unwrap_or(false)));
+        if diagnostics.has_errors() && !options.keep_going {
+            diagnostics.sort();
+            return Err(BlogError::Content(std::mem::take(diagnostics)));
+        }
+    }
+
+    if !options.dev {
+        let summary = crate::minify::minify_output(output_dir, &config.minify)?;
+        if summary.files > 0 {
+            tracing::info!(
+                "Minified {} files: {} -> {} bytes ({} bytes saved)",
+                summary.files,
+                summary.original_bytes,
+                summary.minified_bytes,
+                summary.saved_bytes()
+            );
+        }
+    }
+
+    let summary = crate::compress::compress_output(output_dir, &config.compress)?;
+    if summary.files > 0 {
+        tracing::info!(
+            "Pre-compressed {} files ({} gzip, {} brotli)",
+            summary.files,
+            summary.gzip_files,
+            summary.brotli_files
+        );
+    }
+
     Ok(())
 }
 
 pub fn init_project<P: AsRef<Path>>(path: P) -> Result<()> {
     let path = path.as_ref();
-    fs::create_dir_all(path.join("content/posts"))?;
-    fs::create_dir_all(path.join("themes/default"))?;
-    fs::create_dir_all(path.join("static"))?;
+    create_dir(path.join("content/posts"))?;
+    create_dir(path.join("themes/default"))?;
+    create_dir(path.join("static"))?;
 
     let config_toml = r#"title = "My New Blog"
 base_url = "http://localhost:3000"
@@ -105,7 +375,7 @@ description = "A blog generated with Rust"
 posts_per_page = 10
 theme = "default"
 "#;
-    fs::write(path.join("config.toml"), config_toml)?;
+    write_file(path.join("config.toml"), config_toml)?;
 
     let hello_world = r#"---
 title: Hello World
@@ -116,7 +386,7 @@ slug: hello-world
 
 Welcome to your new blog!
 "#;
-    fs::write(path.join("content/posts/hello-world.md"), hello_world)?;
+    write_file(path.join("content/posts/hello-world.md"), hello_world)?;
 
     let index_html = r#"<!DOCTYPE html>
 <html>
@@ -135,7 +405,7 @@ Welcome to your new blog!
     {% endif %}
 </body>
 </html>"#;
-    fs::write(path.join("themes/default/index.html"), index_html)?;
+    write_file(path.join("themes/default/index.html"), index_html)?;
 
     let post_html = r#"<!DOCTYPE html>
 <html>
@@ -144,10 +414,10 @@ Welcome to your new blog!
     <nav><a href="/">Back to Home</a></nav>
     <h1>{{ post.meta.title }}</h1>
     <p>Published on: {{ post.meta.date }}</p>
-    <div>{{ post.content }}</div>
+    <div>{{ post.content | safe }}</div>
 </body>
 </html>"#;
-    fs::write(path.join("themes/default/post.html"), post_html)?;
+    write_file(path.join("themes/default/post.html"), post_html)?;
 
     let taxonomy_html = r#"<!DOCTYPE html>
 <html>
@@ -162,27 +432,108 @@ Welcome to your new blog!
     </ul>
 </body>
 </html>"#;
-    fs::write(path.join("themes/default/taxonomy.html"), taxonomy_html)?;
+    write_file(path.join("themes/default/taxonomy.html"), taxonomy_html)?;
 
     tracing::info!("Project initialized at {:?}", path);
     Ok(())
 }
 
+pub(crate) fn render_pages(
+    renderer: &Renderer,
+    posts: &[Post],
+    config: &Config,
+    diagnostics: &mut Diagnostics,
+) -> Result<BTreeMap<String, String>> {
+    let mut rendered: Vec<(String, PathBuf, Result<String>)> = Vec::new();
+
+    let per_page = config.posts_per_page.unwrap_or(10);
+    let languages = crate::i18n::language_groups(posts, config);
+    for (lang, posts) in &languages {
+        let home = config.language(lang).url;
+        let prefix = home.trim_start_matches('/');
+        for (i, page) in crate::pagination::paginate_under(posts, per_page, &home).iter().enumerate() {
+            let path = format!("{}index.html", crate::pagination::page_url_under(&home, i + 1).trim_start_matches('/'));
+            rendered.push((path.clone(), PathBuf::from(path), renderer.render_paginated_index(page, config, lang)));
+        }
+
+        for (kind, groups) in [
+            ("tags", crate::taxonomy::group_by_tag(posts)),
+            ("categories", crate::taxonomy::group_by_category(posts)),
+        ] {
+            for (name, group) in groups {
+                let path = format!("{}{}/{}/index.html", prefix, kind, name);
+                rendered.push((path.clone(), PathBuf::from(path), renderer.render_taxonomy(&name, &group, config, lang)));
+            }
+        }
+
+        for series in crate::series::group_by_series(posts, config) {
+            let path = format!("{}index.html", series.url.trim_start_matches('/'));
+            rendered.push((path.clone(), PathBuf::from(path), renderer.render_series(&series, config, lang)));
+        }
+
+        for (author, group) in crate::authors::group_by_author(posts) {
+            let dir = author.url.trim_start_matches('/');
+            let (page, feed) = (format!("{}index.html", dir), format!("{}rss.xml", dir));
+            rendered.push((page.clone(), PathBuf::from(page), renderer.render_author(author, &group, config, lang)));
+            rendered.push((feed.clone(), PathBuf::from(feed), crate::seo::generate_author_rss(author, &group, config, lang)));
+        }
+    }
+
+    for (status, template) in renderer.status_templates() {
+        let html = renderer.render_status_page(status, &template, languages[0].1, config);
+        rendered.push((template.clone(), PathBuf::from(template), html));
+    }
+
+    rendered.par_extend(posts.par_iter().map(|post| (post_path(post), post_source(post), renderer.render_post(post, config))));
+
+    let mut pages = BTreeMap::new();
+    for (path, source, result) in rendered {
+        match result {
+            Ok(html) => {
+                pages.insert(path, html);
+            }
+            Err(e) => diagnostics.push(Diagnostic::error(&source, e.report())),
+        }
+    }
+
+    for (lang, posts) in &languages {
+        let home = config.language(lang).url;
+        let prefix = home.trim_start_matches('/');
+        pages.insert(format!("{}sitemap.xml", prefix), crate::seo::generate_sitemap(posts, config, lang)?);
+        pages.insert(format!("{}rss.xml", prefix), crate::seo::generate_rss(posts, config, lang)?);
+        pages.insert(format!("{}search.json", prefix), crate::seo::generate_search_index(posts)?);
+    }
+    if config.graph.unwrap_or(false) {
+        pages.insert("graph.json".to_string(), crate::graph::generate_graph(posts)?);
+    }
+    Ok(pages)
+}
+
 fn copy_recursive(src: &Path, dst: &Path) -> Result<()> {
     for entry in WalkDir::new(src).into_iter().filter_map(|e| e.ok()) {
         let path = entry.path();
         if path.is_file() {
-            let relative_path = path.strip_prefix(src)?;
+            let relative_path = path.strip_prefix(src).expect("walkdir yields paths under its root");
             let target_path = dst.join(relative_path);
             if let Some(parent) = target_path.parent() {
-                fs::create_dir_all(parent)?;
+                create_dir(parent)?;
             }
-            fs::copy(path, target_path)?;
+            fs::copy(path, target_path).io("copy", path)?;
         }
     }
     Ok(())
 }
 
+fn create_dir(path: impl AsRef<Path>) -> Result<()> {
+    let path = path.as_ref();
+    fs::create_dir_all(path).io("create", path)
+}
+
+fn write_file(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<()> {
+    let path = path.as_ref();
+    fs::write(path, contents).io("write", path)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -195,8 +546,8 @@ mod tests {
         let project_dir = temp_dir.path().join("myblog");
         let output_dir = temp_dir.path().join("public");
 
-        fs::create_dir_all(&project_dir.join("content/posts")).unwrap();
-        fs::create_dir_all(&project_dir.join("themes/default")).unwrap();
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
         
         let config_content = r#"
             title = "Test Blog"
@@ -224,18 +575,24 @@ slug: post-2
         fs::write(project_dir.join("content/posts/post1.md"), post1_content).unwrap();
         fs::write(project_dir.join("content/posts/post2.md"), post2_content).unwrap();
 
-        let index_template = "<h1>Index: {{ config.title }}</h1>Page {{ paginator.current_page }}<ul>{% for post in paginator.items %}<li>{{ post.meta.title }}</li>{% endfor %}</ul>";
+        let index_template = "<link href=\"{{ assets['scss/main.css'] }}\"><h1>Index: {{ config.title }}</h1>Page {{ paginator.current_page }}<ul>{% for post in paginator.items %}<li>{{ post.meta.title }}</li>{% endfor %}</ul>";
         fs::write(project_dir.join("themes/default/index.html"), index_template).unwrap();
 
-        let post_template = "<h1>{{ post.meta.title }}</h1><div>{{ post.content }}</div>";
+        let post_template = "<h1>{{ post.meta.title }}</h1><div>{{ post.content | safe }}</div>";
         fs::write(project_dir.join("themes/default/post.html"), post_template).unwrap();
 
         let taxonomy_template = "<h1>Taxonomy: {{ name }}</h1><ul>{% for post in posts %}<li>{{ post.meta.title }}</li>{% endfor %}</ul>";
         fs::write(project_dir.join("themes/default/taxonomy.html"), taxonomy_template).unwrap();
 
+        let not_found_template = "<h1>Not found</h1>{% for post in recent_posts %}{{ post.meta.title }}{% endfor %}";
+        fs::write(project_dir.join("themes/default/404.html"), not_found_template).unwrap();
+
         fs::create_dir_all(project_dir.join("static/css")).unwrap();
         fs::write(project_dir.join("static/css/style.css"), "body { color: red; }").unwrap();
 
+        fs::create_dir_all(project_dir.join("assets/scss")).unwrap();
+        fs::write(project_dir.join("assets/scss/main.scss"), "$c: red;\nbody { color: $c; }").unwrap();
+
         build_site(&project_dir, &output_dir, false).expect("Failed to build site");
 
         assert!(output_dir.join("index.html").exists());
@@ -248,9 +605,13 @@ slug: post-2
         
         let css_content = fs::read_to_string(output_dir.join("css/style.css")).unwrap();
         assert_eq!(css_content, "body { color: red; }");
+
+        let scss_content = fs::read_to_string(output_dir.join("scss/main.css")).unwrap();
+        assert_eq!(scss_content, "body{color:red}");
         
         let index_html = fs::read_to_string(output_dir.join("index.html")).unwrap();
         assert!(index_html.contains("Index: Test Blog"));
+        assert!(index_html.contains("<link href=\"&#x2F;scss&#x2F;main.css\">"));
         assert!(index_html.contains("Page 1"));
         assert!(index_html.contains("Post 2"));
 
@@ -262,6 +623,9 @@ slug: post-2
         assert!(search_json.contains("Post 1"));
         assert!(search_json.contains("Post 2"));
 
+        let not_found_html = fs::read_to_string(output_dir.join("404.html")).unwrap();
+        assert_eq!(not_found_html, "<h1>Not found</h1>Post 2Post 1");
+
         let post_html = fs::read_to_string(output_dir.join("posts/post-1/index.html")).unwrap();
         assert!(post_html.contains("<h1>Post 1</h1>"));
         assert!(post_html.contains("<h1>P1</h1>"));
@@ -273,8 +637,8 @@ slug: post-2
         let project_dir = temp_dir.path().join("myblog");
         let output_dir = temp_dir.path().join("public");
 
-        fs::create_dir_all(&project_dir.join("content/posts")).unwrap();
-        fs::create_dir_all(&project_dir.join("themes/default")).unwrap();
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
         
         let config_content = r#"
             title = "Test"
@@ -307,4 +671,365 @@ draft: true
         assert!(index_html.contains("Draft Post"));
         assert!(output_dir.join("posts/draft-post/index.html").exists());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_page_bundles() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path().join("myblog");
+        let output_dir = temp_dir.path().join("public");
+        let bundle = project_dir.join("content/posts/bundle");
+
+        fs::create_dir_all(bundle.join("files")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        fs::write(project_dir.join("config.toml"), "title = \"Test\"\nbase_url = \"https://example.com\"\n").unwrap();
+        fs::write(
+            bundle.join("index.md"),
+            "---\ntitle: Bundle\ndate: 2023-01-01\nslug: bundled\n---\n[Data](files/data.csv)\n",
+        )
+        .unwrap();
+        fs::write(bundle.join("files/data.csv"), "a,b").unwrap();
+
+        fs::write(project_dir.join("themes/default/index.html"), "index").unwrap();
+        fs::write(
+            project_dir.join("themes/default/post.html"),
+            "{{ post.content | safe }}{% for r in post.resources %}[{{ r.name }} {{ r.kind }}]{% endfor %}",
+        )
+        .unwrap();
+
+        build_site(&project_dir, &output_dir, false).expect("Failed to build site");
+
+        assert_eq!(fs::read_to_string(output_dir.join("posts/bundled/files/data.csv")).unwrap(), "a,b");
+        let post_html = fs::read_to_string(output_dir.join("posts/bundled/index.html")).unwrap();
+        assert!(post_html.contains(r#"<a href="/posts/bundled/files/data.csv">Data</a>"#), "{}", post_html);
+        assert!(post_html.contains("[files&#x2F;data.csv file]"));
+    }
+
+    #[test]
+    fn test_fingerprinted_template_images() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path().join("myblog");
+        let output_dir = temp_dir.path().join("public");
+
+        fs::create_dir_all(project_dir.join("static/images")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        fs::write(
+            project_dir.join("config.toml"),
+            "title = \"Test\"\nbase_url = \"https://example.com\"\n[assets]\nfingerprint = true\n",
+        )
+        .unwrap();
+        image::RgbImage::from_pixel(300, 200, image::Rgb([200, 40, 40]))
+            .save(project_dir.join("static/images/cover.png"))
+            .unwrap();
+        fs::write(
+            project_dir.join("themes/default/index.html"),
+            "{% set cover = image(path=\"images/cover.png\") %}<img src=\"{{ cover.src }}\" srcset=\"{{ cover.srcset | safe }}\">",
+        )
+        .unwrap();
+        fs::write(project_dir.join("themes/default/post.html"), "post").unwrap();
+
+        build_site(&project_dir, &output_dir, false).expect("Build failed");
+
+        let manifest: BTreeMap<String, String> =
+            serde_json::from_str(&fs::read_to_string(output_dir.join("assets-manifest.json")).unwrap()).unwrap();
+        let hashed = manifest.get("images/cover-300w.png").expect("variant missing from the manifest");
+        assert!(output_dir.join(hashed).exists());
+        assert!(!output_dir.join("images/cover-300w.png").exists());
+        let index = fs::read_to_string(output_dir.join("index.html")).unwrap();
+        assert!(index.contains(&format!("src=\"/{}\"", hashed)), "{}", index);
+
+        let original = &manifest["images/cover.png"];
+        assert_eq!(fs::read(output_dir.join(original)).unwrap(), fs::read(output_dir.join(hashed)).unwrap());
+        let images: Vec<_> = fs::read_dir(output_dir.join("images")).unwrap().collect();
+        assert_eq!(images.len(), manifest.len());
+    }
+
+    #[test]
+    fn test_failed_build_keeps_previous_output() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path().join("myblog");
+        let output_dir = temp_dir.path().join("public");
+
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        fs::write(project_dir.join("config.toml"), "title = \"Test\"\nbase_url = \"https://example.com\"\n").unwrap();
+        fs::write(project_dir.join("content/posts/a.md"), "---\ntitle: A\ndate: 2023-01-01\nslug: a\n---\nA\n").unwrap();
+        fs::write(project_dir.join("themes/default/index.html"), "version 1").unwrap();
+        fs::write(project_dir.join("themes/default/post.html"), "{{ post.meta.title }}").unwrap();
+
+        let options = BuildOptions {
+            keep_builds: 2,
+            ..Default::default()
+        };
+        build_site_with_options(&project_dir, &output_dir, &options).expect("First build failed");
+
+        fs::write(project_dir.join("themes/default/post.html"), "{{ post.meta.missing.field }}").unwrap();
+        let err = build_site_with_options(&project_dir, &output_dir, &options).unwrap_err();
+        assert!(matches!(err, BlogError::BuildFailed { .. }), "{}", err.report());
+        assert!(err.report().contains("was left unchanged"), "{}", err.report());
+        assert_eq!(fs::read_to_string(output_dir.join("index.html")).unwrap(), "version 1");
+        assert!(output_dir.join("posts/a/index.html").exists());
+        assert!(!staging_dir(&output_dir).unwrap().exists());
+
+        fs::write(project_dir.join("themes/default/post.html"), "{{ post.meta.title }}").unwrap();
+        for version in 2..=4 {
+            fs::write(project_dir.join("themes/default/index.html"), format!("version {}", version)).unwrap();
+            build_site_with_options(&project_dir, &output_dir, &options).expect("Rebuild failed");
+        }
+
+        assert_eq!(fs::read_to_string(output_dir.join("index.html")).unwrap(), "version 4");
+        let mut builds: Vec<_> = fs::read_dir(builds_dir(&output_dir).unwrap())
+            .unwrap()
+            .map(|e| fs::read_to_string(e.unwrap().path().join("index.html")).unwrap())
+            .collect();
+        builds.sort();
+        assert_eq!(builds, vec!["version 2", "version 3"]);
+
+        // A build that cannot be pruned only logs a warning, the new output is live regardless.
+        fs::write(builds_dir(&output_dir).unwrap().join("0-stray"), "not a build").unwrap();
+        fs::write(project_dir.join("themes/default/index.html"), "version 5").unwrap();
+        build_site(&project_dir, &output_dir, false).expect("Rebuild failed");
+        assert_eq!(fs::read_to_string(output_dir.join("index.html")).unwrap(), "version 5");
+        assert!(builds_dir(&output_dir).unwrap().join("0-stray").exists());
+
+        fs::remove_file(builds_dir(&output_dir).unwrap().join("0-stray")).unwrap();
+        build_site(&project_dir, &output_dir, false).expect("Rebuild failed");
+        assert!(!builds_dir(&output_dir).unwrap().exists());
+    }
+
+    #[test]
+    fn test_keep_going() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path().join("myblog");
+        let output_dir = temp_dir.path().join("public");
+
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        fs::write(project_dir.join("config.toml"), "title = \"Test\"\nbase_url = \"https://example.com\"\n").unwrap();
+        fs::write(project_dir.join("content/posts/good.md"), "---\ntitle: Good\ndate: 2023-01-01\nslug: good\n---\nA\n").unwrap();
+        fs::write(project_dir.join("content/posts/broken.md"), "---\ntitle: [\n---\nB\n").unwrap();
+        fs::write(
+            project_dir.join("content/posts/custom.md"),
+            "---\ntitle: Custom\ndate: 2023-01-02\nslug: custom\ntemplate: missing\n---\nC\n",
+        )
+        .unwrap();
+        fs::write(project_dir.join("themes/default/index.html"), "{% for post in paginator.items %}{{ post.meta.title }}{% endfor %}").unwrap();
+        fs::write(project_dir.join("themes/default/post.html"), "{{ post.meta.title }}").unwrap();
+
+        let err = build_site(&project_dir, &output_dir, false).unwrap_err();
+        assert_eq!(err.diagnostics().map(|d| d.error_count()), Some(2));
+        let message = err.report();
+        assert!(message.contains("broken.md:2"), "{}", message);
+        assert!(message.contains("custom.md: Post `custom` (Custom) requests template `missing.html`"), "{}", message);
+        assert!(message.ends_with("2 errors, 0 warnings in 2 files"), "{}", message);
+        assert!(!output_dir.exists());
+
+        let options = BuildOptions {
+            keep_going: true,
+            ..Default::default()
+        };
+        let err = build_site_with_options(&project_dir, &output_dir, &options).unwrap_err();
+        assert!(matches!(err, BlogError::Incomplete(_)), "{}", err.report());
+        assert!(err.report().ends_with("2 errors, 0 warnings in 2 files"));
+        assert_eq!(fs::read_to_string(output_dir.join("index.html")).unwrap(), "Good");
+        assert!(output_dir.join("posts/good/index.html").exists());
+        assert!(!output_dir.join("posts/custom/index.html").exists());
+    }
+
+    #[test]
+    fn test_link_check() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path().join("myblog");
+        let output_dir = temp_dir.path().join("public");
+
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        let config = "title = \"Test\"\nbase_url = \"https://example.com\"\n[links]\nreport = \"links.json\"\n";
+        fs::write(project_dir.join("config.toml"), config).unwrap();
+        fs::write(
+            project_dir.join("content/posts/a.md"),
+            "---\ntitle: A\ndate: 2023-01-01\nslug: a\n---\n[Old](/posts/renamed/) [Docs](https://docs.rs/)\n",
+        )
+        .unwrap();
+        fs::write(project_dir.join("themes/default/index.html"), "index").unwrap();
+        fs::write(project_dir.join("themes/default/post.html"), "{{ post.content | safe }}").unwrap();
+
+        build_site(&project_dir, &output_dir, false).expect("Broken links should only warn by default");
+        let report: serde_json::Value = serde_json::from_str(&fs::read_to_string(project_dir.join("links.json")).unwrap()).unwrap();
+        assert_eq!(report["broken"][0]["page"], "posts/a/index.html");
+        assert_eq!(report["broken"][0]["target"], "/posts/renamed/");
+        assert_eq!(report["broken"][0]["problem"], "missing_target");
+        assert_eq!(report["external"]["https://docs.rs/"][0], "posts/a/index.html");
+
+        fs::write(project_dir.join("config.toml"), format!("{}fatal = true\n", config)).unwrap();
+        let err = build_site(&project_dir, &output_dir, false).unwrap_err();
+        let message = err.report();
+        assert!(message.contains("a.md: Link to `/posts/renamed/` on /posts/a/index.html does not match any page or file"), "{}", message);
+        assert!(message.contains("was left unchanged"), "{}", message);
+    }
+
+    #[test]
+    fn test_backlinks_and_graph() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path().join("myblog");
+        let output_dir = temp_dir.path().join("public");
+
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        fs::write(project_dir.join("config.toml"), "title = \"Test\"\nbase_url = \"https://example.com\"\ngraph = true\n").unwrap();
+        fs::write(project_dir.join("content/posts/a.md"), "---\ntitle: A\ndate: 2023-01-01\nslug: a\n---\nSee [[b]].\n").unwrap();
+        fs::write(project_dir.join("content/posts/b.md"), "---\ntitle: B\ndate: 2023-01-02\nslug: b\n---\nB\n").unwrap();
+        fs::write(project_dir.join("themes/default/index.html"), "index").unwrap();
+        fs::write(
+            project_dir.join("themes/default/post.html"),
+            "{% for link in post.backlinks %}[{{ link.title }}]{% endfor %}",
+        )
+        .unwrap();
+
+        build_site(&project_dir, &output_dir, false).expect("Build failed");
+        assert_eq!(fs::read_to_string(output_dir.join("posts/b/index.html")).unwrap(), "[A]");
+        assert_eq!(fs::read_to_string(output_dir.join("posts/a/index.html")).unwrap(), "");
+        let graph = fs::read_to_string(output_dir.join("graph.json")).unwrap();
+        assert!(graph.contains(r#""edges":[{"source":"/posts/a/","target":"/posts/b/"}]"#), "{}", graph);
+    }
+
+    #[test]
+    fn test_series() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path().join("myblog");
+        let output_dir = temp_dir.path().join("public");
+
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        fs::write(project_dir.join("config.toml"), "title = \"Test\"\nbase_url = \"https://example.com\"\n").unwrap();
+        fs::write(project_dir.join("content/posts/a.md"), "---\ntitle: A\ndate: 2023-01-02\nslug: a\nseries: Intro to Rust\nseries_order: 1\n---\nA\n").unwrap();
+        fs::write(project_dir.join("content/posts/b.md"), "---\ntitle: B\ndate: 2023-01-01\nslug: b\nseries: Intro to Rust\nseries_order: 2\n---\nB\n").unwrap();
+        fs::write(project_dir.join("themes/default/index.html"), "index").unwrap();
+        fs::write(project_dir.join("themes/default/taxonomy.html"), "{{ name }}:{% for post in posts %}{{ post.meta.slug }}{% endfor %}").unwrap();
+        fs::write(
+            project_dir.join("themes/default/post.html"),
+            "Part {{ post.series.index }} of {{ post.series.total }}{% if post.series.next %} next={{ post.series.next.slug }}{% endif %}",
+        )
+        .unwrap();
+
+        build_site(&project_dir, &output_dir, false).expect("Build failed");
+        assert_eq!(fs::read_to_string(output_dir.join("posts/a/index.html")).unwrap(), "Part 1 of 2 next=b");
+        assert_eq!(fs::read_to_string(output_dir.join("posts/b/index.html")).unwrap(), "Part 2 of 2");
+        assert_eq!(fs::read_to_string(output_dir.join("series/intro-to-rust/index.html")).unwrap(), "Intro to Rust:ab");
+
+        fs::write(project_dir.join("themes/default/series.html"), "{{ series.title }} ({{ series.url }})").unwrap();
+        build_site(&project_dir, &output_dir, false).expect("Build failed");
+        assert_eq!(
+            fs::read_to_string(output_dir.join("series/intro-to-rust/index.html")).unwrap(),
+            "Intro to Rust (&#x2F;series&#x2F;intro-to-rust&#x2F;)"
+        );
+    }
+
+    #[test]
+    fn test_author_pages() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path().join("myblog");
+        let output_dir = temp_dir.path().join("public");
+
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        fs::write(
+            project_dir.join("config.toml"),
+            "title = \"Test\"\nbase_url = \"https://example.com\"\n[authors.ada]\nname = \"Ada Lovelace\"\n",
+        )
+        .unwrap();
+        fs::write(project_dir.join("content/posts/a.md"), "---\ntitle: A\ndate: 2023-01-01\nslug: a\nauthors: [ada]\n---\nA\n").unwrap();
+        fs::write(project_dir.join("content/posts/b.md"), "---\ntitle: B\ndate: 2023-01-02\nslug: b\n---\nB\n").unwrap();
+        fs::write(project_dir.join("themes/default/index.html"), "index").unwrap();
+        fs::write(project_dir.join("themes/default/post.html"), "{% for author in post.authors %}{{ author.name }}{% endfor %}").unwrap();
+        fs::write(project_dir.join("themes/default/author.html"), "{{ author.name }}:{% for post in posts %}{{ post.meta.slug }}{% endfor %}").unwrap();
+
+        build_site(&project_dir, &output_dir, false).expect("Build failed");
+        assert_eq!(fs::read_to_string(output_dir.join("posts/a/index.html")).unwrap(), "Ada Lovelace");
+        assert_eq!(fs::read_to_string(output_dir.join("authors/ada/index.html")).unwrap(), "Ada Lovelace:a");
+        let feed = fs::read_to_string(output_dir.join("authors/ada/rss.xml")).unwrap();
+        assert!(feed.contains("<dc:creator>Ada Lovelace</dc:creator>"), "{}", feed);
+        assert!(!feed.contains("<title>B</title>"), "{}", feed);
+    }
+
+    #[test]
+    fn test_multilingual_site() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path().join("myblog");
+        let output_dir = temp_dir.path().join("public");
+
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        fs::write(
+            project_dir.join("config.toml"),
+            concat!(
+                "title = \"Blog\"\nbase_url = \"https://example.com\"\ngraph = true\n",
+                "[languages.es]\ntitle = \"Bitácora\"\n[languages.es.translations]\nread_more = \"Leer más\"\n",
+                "[languages.en.translations]\nread_more = \"Read more\"\n",
+            ),
+        )
+        .unwrap();
+        fs::write(project_dir.join("content/posts/hello.md"), "---\ntitle: Hello\ndate: 2023-01-01\nslug: hello\ntags: [rust]\n---\nSee [[hello]].\n").unwrap();
+        fs::write(project_dir.join("content/posts/hello.es.md"), "---\ntitle: Hola\ndate: 2023-01-01\nslug: hello\ntags: [rust]\n---\nVer [[hello]].\n").unwrap();
+        fs::write(project_dir.join("content/posts/only.md"), "---\ntitle: Only\ndate: 2023-01-02\nslug: only\n---\nOnly\n").unwrap();
+        fs::write(
+            project_dir.join("themes/default/index.html"),
+            "{{ lang }} {{ language.title }}:{% for post in paginator.items %}{{ post.url }} {% endfor %}{{ trans(key=\"read_more\", lang=lang) }}",
+        )
+        .unwrap();
+        fs::write(project_dir.join("themes/default/taxonomy.html"), "{{ lang }}:{% for post in posts %}{{ post.meta.title }}{% endfor %}").unwrap();
+        fs::write(
+            project_dir.join("themes/default/post.html"),
+            "<html lang=\"{{ lang }}\">{% for t in post.translations %}<link rel=\"alternate\" hreflang=\"{{ t.lang }}\" href=\"{{ t.url | safe }}\">{% endfor %}{{ post.content | safe }}{% if next %}{{ next.slug }}{% endif %}",
+        )
+        .unwrap();
+
+        build_site(&project_dir, &output_dir, false).expect("Build failed");
+        let read = |path: &str| fs::read_to_string(output_dir.join(path)).unwrap();
+        assert_eq!(read("index.html"), "en Blog:&#x2F;posts&#x2F;only&#x2F; &#x2F;posts&#x2F;hello&#x2F; Read more");
+        assert_eq!(read("es/index.html"), "es Bitácora:&#x2F;es&#x2F;posts&#x2F;hello&#x2F; Leer más");
+        assert_eq!(read("tags/rust/index.html"), "en:Hello");
+        assert_eq!(read("es/tags/rust/index.html"), "es:Hola");
+
+        let hola = read("es/posts/hello/index.html");
+        assert!(hola.starts_with("<html lang=\"es\"><link rel=\"alternate\" hreflang=\"en\" href=\"/posts/hello/\">"), "{}", hola);
+        assert!(hola.contains("<a href=\"/es/posts/hello/\">Hola</a>"), "{}", hola);
+        assert!(!hola.ends_with("only"), "{}", hola);
+        assert!(read("posts/hello/index.html").ends_with("only"));
+
+        let sitemap = read("sitemap.xml");
+        assert!(sitemap.contains("<loc>https://example.com/posts/hello/</loc><xhtml:link rel=\"alternate\" hreflang=\"en\" href=\"https://example.com/posts/hello/\"/><xhtml:link rel=\"alternate\" hreflang=\"es\" href=\"https://example.com/es/posts/hello/\"/>"), "{}", sitemap);
+        assert!(!sitemap.contains("<loc>https://example.com/es/"), "{}", sitemap);
+        assert!(read("es/sitemap.xml").contains("<loc>https://example.com/es/</loc>"));
+        assert!(read("es/rss.xml").contains("<title>Bitácora</title>"));
+        assert!(!read("rss.xml").contains("Hola"));
+
+        let graph: serde_json::Value = serde_json::from_str(&read("graph.json")).unwrap();
+        let ids: Vec<&str> = graph["nodes"].as_array().unwrap().iter().map(|n| n["id"].as_str().unwrap()).collect();
+        assert_eq!(ids, vec!["/posts/only/", "/posts/hello/", "/es/posts/hello/"]);
+    }
+
+    #[test]
+    fn test_data_files() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project_dir = temp_dir.path().join("myblog");
+        let output_dir = temp_dir.path().join("public");
+
+        fs::create_dir_all(project_dir.join("content/posts")).unwrap();
+        fs::create_dir_all(project_dir.join("themes/default")).unwrap();
+        fs::create_dir_all(project_dir.join("data/sidebar")).unwrap();
+        fs::write(project_dir.join("config.toml"), "title = \"Test\"\nbase_url = \"https://example.com\"\n").unwrap();
+        fs::write(project_dir.join("content/posts/a.md"), "---\ntitle: A\ndate: 2023-01-01\nslug: a\n---\nA\n").unwrap();
+        fs::write(project_dir.join("data/sidebar/blogroll.csv"), "name,url\nAlice,https://alice.example\n").unwrap();
+        fs::write(project_dir.join("themes/default/index.html"), "{% for b in data.sidebar.blogroll %}{{ b.name }}{% endfor %}").unwrap();
+        fs::write(project_dir.join("themes/default/post.html"), "{{ data.sidebar.blogroll | length }}").unwrap();
+
+        build_site(&project_dir, &output_dir, false).expect("Build failed");
+        assert_eq!(fs::read_to_string(output_dir.join("index.html")).unwrap(), "Alice");
+        assert_eq!(fs::read_to_string(output_dir.join("posts/a/index.html")).unwrap(), "1");
+
+        fs::write(project_dir.join("data/sidebar/blogroll.csv"), "name,url\nAlice\n").unwrap();
+        let err = build_site(&project_dir, &output_dir, false).unwrap_err();
+        assert!(err.report().contains("blogroll.csv\" at line 2: expected 2 fields, found 1"), "{}", err.report());
+        assert_eq!(fs::read_to_string(output_dir.join("index.html")).unwrap(), "Alice");
+    }
+}
@@ -0,0 +1,148 @@
+use crate::diagnostics::Diagnostics;
+use std::error::Error as _;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub type Result<T, E = BlogError> = std::result::Result<T, E>;
+
+#[derive(Debug, thiserror::Error)]
+pub enum BlogError {
+    #[error("Failed to {action} {path:?}")]
+    Io {
+        action: &'static str,
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    #[error("Failed to parse config file {path:?}")]
+    Config {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+
+    #[error("{0}")]
+    InvalidConfig(String),
+
+    #[error("{message}")]
+    Frontmatter {
+        path: PathBuf,
+        line: usize,
+        column: Option<usize>,
+        message: String,
+    },
+
+    #[error("Cross-reference `{target}` does not match any post")]
+    BrokenReference {
+        path: PathBuf,
+        line: usize,
+        column: Option<usize>,
+        target: String,
+    },
+
+    #[error("Failed to parse data file {path:?} at line {line}: {message}")]
+    Data {
+        path: PathBuf,
+        line: usize,
+        column: Option<usize>,
+        message: String,
+    },
+
+    #[error("Failed to parse theme manifest {path:?}")]
+    ThemeManifest {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+
+    #[error("{message}")]
+    Theme { theme: String, message: String },
+
+    #[error("Failed to load templates for theme `{theme}`:\n{resolution}")]
+    TemplateLoad {
+        theme: String,
+        resolution: String,
+        #[source]
+        source: tera::Error,
+    },
+
+    #[error("Post `{slug}` ({title}) requests template `{template}`, which does not exist in the theme")]
+    MissingTemplate { slug: String, title: String, template: String },
+
+    #[error("{message}")]
+    Render {
+        template: String,
+        message: String,
+        #[source]
+        source: tera::Error,
+    },
+
+    #[error("{message}")]
+    Asset { path: PathBuf, message: String },
+
+    #[error("Failed to process image {path:?}")]
+    Image {
+        path: PathBuf,
+        #[source]
+        source: image::ImageError,
+    },
+
+    #[error("Failed to encode JSON")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Failed to watch for changes")]
+    Watch(#[from] notify_debouncer_mini::notify::Error),
+
+    #[error("{0}")]
+    Content(Diagnostics),
+
+    #[error("Build failed, {output:?} was left unchanged")]
+    BuildFailed {
+        output: PathBuf,
+        #[source]
+        source: Box<BlogError>,
+    },
+
+    #[error("Build completed, skipping content with errors")]
+    Incomplete(#[source] Diagnostics),
+}
+
+impl BlogError {
+    pub fn io(action: &'static str, path: impl AsRef<Path>, source: io::Error) -> Self {
+        BlogError::Io {
+            action,
+            path: path.as_ref().to_path_buf(),
+            source,
+        }
+    }
+
+    pub fn diagnostics(&self) -> Option<&Diagnostics> {
+        match self {
+            BlogError::Content(diagnostics) | BlogError::Incomplete(diagnostics) => Some(diagnostics),
+            BlogError::BuildFailed { source, .. } => source.diagnostics(),
+            _ => None,
+        }
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = self.to_string();
+        let mut source = self.source();
+        while let Some(error) = source {
+            report.push_str(": ");
+            report.push_str(&error.to_string());
+            source = error.source();
+        }
+        report
+    }
+}
+
+pub(crate) trait IoResultExt<T> {
+    fn io(self, action: &'static str, path: impl AsRef<Path>) -> Result<T>;
+}
+
+impl<T> IoResultExt<T> for io::Result<T> {
+    fn io(self, action: &'static str, path: impl AsRef<Path>) -> Result<T> {
+        self.map_err(|source| BlogError::io(action, path, source))
+    }
+}
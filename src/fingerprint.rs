@@ -0,0 +1,285 @@
+use crate::html::{decode_entities, resolve_local_url, rewrite_tags};
+use crate::error::{IoResultExt, Result};
+use base64::Engine;
+use sha2::{Digest, Sha256, Sha384};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+const FINGERPRINT_EXTENSIONS: &[&str] = &[
+    "css", "js", "mjs", "png", "jpg", "jpeg", "gif", "svg", "webp", "avif", "woff", "woff2", "ttf", "otf", "eot", "mp4", "webm",
+];
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AssetManifest {
+    pub files: BTreeMap<String, String>,
+    pub integrity: BTreeMap<String, String>,
+}
+
+impl AssetManifest {
+    pub fn url(&self, path: &str) -> Option<String> {
+        self.files.get(path.trim_start_matches('/')).map(|hashed| format!("/{}", hashed))
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.files)?)
+    }
+}
+
+pub fn fingerprint_assets(output_dir: &Path) -> Result<AssetManifest> {
+    let candidates: Vec<String> = WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let relative = relative_name(output_dir, e.path())?;
+            FINGERPRINT_EXTENSIONS.contains(&extension(&relative)).then_some(relative)
+        })
+        .collect();
+    let mut manifest = AssetManifest::default();
+    fingerprint_files(output_dir, candidates, &mut manifest)?;
+    Ok(manifest)
+}
+
+pub fn fingerprint_files(output_dir: &Path, mut candidates: Vec<String>, manifest: &mut AssetManifest) -> Result<()> {
+    candidates.sort_by_key(|name| (extension(name) == "css", name.clone()));
+
+    for relative in candidates {
+        let path = output_dir.join(&relative);
+        let mut bytes = fs::read(&path).io("read asset", &path)?;
+
+        if extension(&relative) == "css" {
+            let css = String::from_utf8_lossy(&bytes);
+            let base_dir = relative.rsplit_once('/').map_or("", |(dir, _)| dir);
+            bytes = rewrite_css_urls(&css, base_dir, manifest).into_bytes();
+        }
+
+        let digest = Sha256::digest(&bytes);
+        let hash: String = digest.iter().take(4).map(|b| format!("{:02x}", b)).collect();
+        let hashed = hashed_name(&relative, &hash);
+
+        let target = output_dir.join(&hashed);
+        fs::write(&target, &bytes).io("write asset", &target)?;
+        fs::remove_file(&path).io("remove asset", &path)?;
+
+        manifest.integrity.insert(hashed.clone(), integrity(&bytes));
+        if let Some(previous) = manifest.files.insert(relative, hashed.clone()).filter(|previous| *previous != hashed) {
+            // The file was rewritten after an earlier pass, drop the stale copy.
+            manifest.integrity.remove(&previous);
+            let previous = output_dir.join(previous);
+            fs::remove_file(&previous).io("remove asset", &previous)?;
+        }
+    }
+
+    Ok(())
+}
+
+pub fn rewrite_html_references(output_dir: &Path, manifest: &AssetManifest, base_url: &str, add_integrity: bool) -> Result<()> {
+    if manifest.files.is_empty() {
+        return Ok(());
+    }
+
+    for entry in WalkDir::new(output_dir).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() || entry.path().extension().is_none_or(|ext| ext != "html") {
+            continue;
+        }
+        let Some(relative) = relative_name(output_dir, entry.path()) else {
+            continue;
+        };
+        let base_dir = relative.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+        let html = fs::read_to_string(entry.path()).io("read", entry.path())?;
+        let rewritten = rewrite_html(&html, base_dir, manifest, base_url, add_integrity);
+        if rewritten != html {
+            fs::write(entry.path(), rewritten).io("write", entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+pub fn rewrite_html(html: &str, base_dir: &str, manifest: &AssetManifest, base_url: &str, add_integrity: bool) -> String {
+    rewrite_tags(html, |tag| {
+        let mut integrity_for = None;
+        for (key, value) in tag.attrs.iter_mut() {
+            let Some(url) = value.as_mut() else {
+                continue;
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "href" | "src" | "poster" => {
+                    // Template output is autoescaped, so `/` may arrive as `&#x2F;`.
+                    if let Some((hashed, path)) = hashed_url(&decode_entities(url), base_dir, manifest, base_url) {
+                        integrity_for = manifest.integrity.get(&path).cloned();
+                        *url = hashed.replace('&', "&amp;");
+                    }
+                }
+                "srcset" => {
+                    let mut changed = false;
+                    let candidates: Vec<String> = decode_entities(url)
+                        .split(',')
+                        .map(|candidate| {
+                            let candidate = candidate.trim();
+                            let (src, descriptor) = candidate.split_once(' ').unwrap_or((candidate, ""));
+                            let src = match hashed_url(src, base_dir, manifest, base_url) {
+                                Some((hashed, _)) => {
+                                    changed = true;
+                                    hashed
+                                }
+                                None => src.to_string(),
+                            };
+                            format!("{} {}", src, descriptor).trim_end().to_string()
+                        })
+                        .collect();
+                    if changed {
+                        *url = candidates.join(", ").replace('&', "&amp;");
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let supports_integrity = match tag.name.as_str() {
+            "script" => tag.has_attr("src"),
+            "link" => tag
+                .attr("rel")
+                .is_some_and(|rel| rel.split_whitespace().any(|r| matches!(r, "stylesheet" | "preload" | "modulepreload"))),
+            _ => false,
+        };
+        if let Some(hash) = integrity_for {
+            if add_integrity && supports_integrity && !tag.has_attr("integrity") {
+                tag.set_attr("integrity", &hash);
+            }
+        }
+    })
+}
+
+fn hashed_url(url: &str, base_dir: &str, manifest: &AssetManifest, base_url: &str) -> Option<(String, String)> {
+    let local = resolve_local_url(url, base_dir, base_url)?;
+    let hashed = manifest.files.get(&local.path)?;
+    let prefix = if url.starts_with(base_url.trim_end_matches('/')) && !base_url.is_empty() {
+        base_url.trim_end_matches('/')
+    } else {
+        ""
+    };
+    let query = if local.query.starts_with("?h=") { "" } else { &local.query };
+    Some((format!("{}/{}{}{}", prefix, hashed, query, local.fragment), hashed.clone()))
+}
+
+pub fn rewrite_css_urls(css: &str, base_dir: &str, manifest: &AssetManifest) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = rest.find("url(") {
+        let (before, after) = rest.split_at(start + 4);
+        output.push_str(before);
+        let Some(end) = after.find(')') else {
+            rest = after;
+            break;
+        };
+        let raw = &after[..end];
+        let trimmed = raw.trim();
+        let quote = trimmed.chars().next().filter(|c| *c == '"' || *c == '\'');
+        let url = match quote {
+            Some(q) => trimmed.trim_matches(q),
+            None => trimmed,
+        };
+        match hashed_url(url, base_dir, manifest, "") {
+            Some((hashed, _)) => {
+                let q = quote.map(String::from).unwrap_or_default();
+                output.push_str(&format!("{}{}{}", q, hashed, q));
+            }
+            None => output.push_str(raw),
+        }
+        rest = &after[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+pub fn integrity(bytes: &[u8]) -> String {
+    format!("sha384-{}", base64::engine::general_purpose::STANDARD.encode(Sha384::digest(bytes)))
+}
+
+fn hashed_name(relative: &str, hash: &str) -> String {
+    let (dir, file) = match relative.rsplit_once('/') {
+        Some((dir, file)) => (format!("{}/", dir), file),
+        None => (String::new(), relative),
+    };
+    match file.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => format!("{}{}.{}.{}", dir, stem, hash, ext),
+        _ => format!("{}{}.{}", dir, file, hash),
+    }
+}
+
+fn relative_name(base: &Path, path: &Path) -> Option<String> {
+    Some(
+        path.strip_prefix(base)
+            .ok()?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+fn extension(name: &str) -> &str {
+    Path::new(name).extension().and_then(|e| e.to_str()).unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_fingerprint_assets() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let output = temp_dir.path();
+        fs::create_dir_all(output.join("css")).unwrap();
+        fs::create_dir_all(output.join("img")).unwrap();
+        fs::write(output.join("css/style.css"), "body{background:url(\"../img/bg.png\")}").unwrap();
+        fs::write(output.join("img/bg.png"), "png").unwrap();
+        fs::write(output.join("robots.txt"), "User-agent: *").unwrap();
+
+        let manifest = fingerprint_assets(output).expect("Failed to fingerprint assets");
+
+        let png = manifest.files["img/bg.png"].clone();
+        let css = manifest.files["css/style.css"].clone();
+        assert!(png.starts_with("img/bg.") && png.ends_with(".png") && png.len() == "img/bg.12345678.png".len(), "{}", png);
+        assert!(css.starts_with("css/style.") && css.ends_with(".css"), "{}", css);
+        assert!(!manifest.files.contains_key("robots.txt"));
+
+        assert!(!output.join("css/style.css").exists());
+        assert!(output.join("robots.txt").exists());
+        let css_content = fs::read_to_string(output.join(&css)).unwrap();
+        assert_eq!(css_content, format!("body{{background:url(\"/{}\")}}", png));
+        assert_eq!(manifest.integrity[&css], integrity(css_content.as_bytes()));
+        assert!(manifest.integrity[&css].starts_with("sha384-"));
+    }
+
+    #[test]
+    fn test_rewrite_html() {
+        let mut manifest = AssetManifest::default();
+        manifest.files.insert("css/style.css".to_string(), "css/style.abcd1234.css".to_string());
+        manifest.files.insert("js/app.js".to_string(), "js/app.ffff0000.js".to_string());
+        manifest.files.insert("img/a.png".to_string(), "img/a.11112222.png".to_string());
+        manifest.integrity.insert("css/style.abcd1234.css".to_string(), "sha384-css".to_string());
+        manifest.integrity.insert("js/app.ffff0000.js".to_string(), "sha384-js".to_string());
+
+        let html = r#"<link rel="stylesheet" href="/css/style.css?h=0011"><script src="https://example.com/js/app.js"></script><img src="../../img/a.png" srcset="/img/a.png 1x, /img/b.png 2x"><a href="/about/">About</a>"#;
+        let output = rewrite_html(html, "posts/hello", &manifest, "https://example.com", true);
+
+        assert_eq!(
+            output,
+            r#"<link rel="stylesheet" href="/css/style.abcd1234.css" integrity="sha384-css"><script src="https://example.com/js/app.ffff0000.js" integrity="sha384-js"></script><img src="/img/a.11112222.png" srcset="/img/a.11112222.png 1x, /img/b.png 2x"><a href="/about/">About</a>"#
+        );
+
+        let without_sri = rewrite_html(html, "posts/hello", &manifest, "https://example.com", false);
+        assert!(!without_sri.contains("integrity"));
+
+        let escaped = r#"<link rel="stylesheet" href="https:&#x2F;&#x2F;example.com&#x2F;css&#x2F;style.css?v=1&amp;x=2"><img srcset="&#x2F;img&#x2F;a.png 1x">"#;
+        assert_eq!(
+            rewrite_html(escaped, "", &manifest, "https://example.com", true),
+            r#"<link rel="stylesheet" href="https://example.com/css/style.abcd1234.css?v=1&amp;x=2" integrity="sha384-css"><img srcset="/img/a.11112222.png 1x">"#
+        );
+    }
+}
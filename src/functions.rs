@@ -0,0 +1,479 @@
+use crate::domain::{Config, Post};
+use crate::images::{ImageProcessor, ProcessedImage};
+use chrono::{DateTime, NaiveDate};
+use pulldown_cmark::{html, Options, Parser};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tera::{Filter, Function, Tera, Value};
+
+pub fn register_filters(tera: &mut Tera) {
+    tera.register_filter("date_format", date_format);
+    tera.register_filter("markdown", MarkdownFilter);
+    tera.register_filter("slugify", |value: &Value, _: &HashMap<String, Value>| {
+        Ok(Value::String(slugify(&string_arg(value, "slugify")?)))
+    });
+    tera.register_filter("reading_time", reading_time_filter);
+}
+
+pub fn register_site_functions(
+    tera: &mut Tera,
+    posts: &[Post],
+    config: &Config,
+    static_dirs: Vec<PathBuf>,
+    fingerprinted: BTreeMap<String, String>,
+) {
+    let base_url = config.base_url.trim_end_matches('/').to_string();
+
+    let default_language = config.default_language().to_string();
+    let mut by_slug: BTreeMap<(String, String), Value> = BTreeMap::new();
+    for post in posts {
+        let lang = config.language(&post.lang).code;
+        by_slug.entry((lang, post.meta.slug.clone())).or_insert_with(|| to_value(post));
+    }
+
+    let mut taxonomies = BTreeMap::new();
+    for (lang, posts) in crate::i18n::language_groups(posts, config) {
+        let prefix = config.language_prefix(&lang);
+        let tags = taxonomy_value(&prefix, "tags", &crate::taxonomy::group_by_tag(posts));
+        let categories = taxonomy_value(&prefix, "categories", &crate::taxonomy::group_by_category(posts));
+        taxonomies.insert((lang.clone(), "tags"), tags);
+        taxonomies.insert((lang, "categories"), categories);
+    }
+
+    let translations: BTreeMap<String, BTreeMap<String, String>> = config
+        .languages
+        .iter()
+        .map(|(lang, language)| (lang.clone(), language.translations.clone()))
+        .collect();
+
+    let url_base = base_url.clone();
+    tera.register_function("get_url", move |args: &HashMap<String, Value>| {
+        let path = required_arg(args, "get_url", "path")?;
+        Ok(Value::String(absolute_url(&url_base, &path)))
+    });
+
+    let post_language = default_language.clone();
+    tera.register_function("get_post", move |args: &HashMap<String, Value>| {
+        let slug = required_arg(args, "get_post", "slug")?;
+        let lang = optional_arg(args, "get_post", "lang")?.unwrap_or_else(|| post_language.clone());
+        by_slug
+            .get(&(lang, slug.clone()))
+            .cloned()
+            .ok_or_else(|| tera::Error::msg(format!("get_post: no post with slug `{}`", slug)))
+    });
+
+    let taxonomy_language = default_language.clone();
+    tera.register_function("get_taxonomy", move |args: &HashMap<String, Value>| {
+        let kind = required_arg(args, "get_taxonomy", "kind")?;
+        let lang = optional_arg(args, "get_taxonomy", "lang")?.unwrap_or_else(|| taxonomy_language.clone());
+        if kind != "tags" && kind != "categories" {
+            return Err(tera::Error::msg(format!("get_taxonomy: unknown kind `{}`, expected `tags` or `categories`", kind)));
+        }
+        Ok(taxonomies.get(&(lang, kind.as_str())).cloned().unwrap_or(Value::Array(Vec::new())))
+    });
+
+    tera.register_function("trans", move |args: &HashMap<String, Value>| {
+        let key = required_arg(args, "trans", "key")?;
+        let lang = optional_arg(args, "trans", "lang")?.unwrap_or_else(|| default_language.clone());
+        [lang.as_str(), default_language.as_str()]
+            .iter()
+            .find_map(|lang| translations.get(*lang)?.get(&key))
+            .map(|text| Value::String(text.clone()))
+            .ok_or_else(|| tera::Error::msg(format!("trans: no translation for `{}` in `{}`", key, lang)))
+    });
+
+    tera.register_function(
+        "asset_url",
+        AssetUrl {
+            static_dirs,
+            fingerprinted,
+            hashes: Mutex::new(HashMap::new()),
+        },
+    );
+
+    tera.register_function(
+        "json_ld",
+        JsonLd {
+            base_url,
+            site_title: config.title.clone(),
+            site_description: config.description.clone(),
+        },
+    );
+}
+
+pub fn register_image_function(tera: &mut Tera, images: Arc<ImageProcessor>) {
+    tera.register_function("image", move |args: &HashMap<String, Value>| {
+        let path = required_arg(args, "image", "path")?;
+        let processed = images
+            .process(&path)
+            .map_err(|e| tera::Error::msg(format!("image: {}", e.report())))?
+            .unwrap_or_else(|| ProcessedImage {
+                src: format!("/{}", path.trim_start_matches('/')),
+                ..Default::default()
+            });
+        Ok(to_value(processed))
+    });
+}
+
+pub fn slugify(input: &str) -> String {
+    let mut slug = String::with_capacity(input.len());
+    for c in input.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            slug.push(c);
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
+pub fn reading_time(html: &str, words_per_minute: usize) -> usize {
+    let words = strip_tags(html).split_whitespace().count();
+    words.div_ceil(words_per_minute.max(1)).max(1)
+}
+
+pub fn strip_tags(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+pub fn absolute_url(base_url: &str, path: &str) -> String {
+    format!("{}/{}", base_url.trim_end_matches('/'), path.trim_start_matches('/'))
+}
+
+fn to_value<T: serde::Serialize>(value: T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn taxonomy_value(prefix: &str, kind: &str, groups: &HashMap<String, Vec<&Post>>) -> Value {
+    let mut names: Vec<&String> = groups.keys().collect();
+    names.sort();
+    Value::Array(
+        names
+            .into_iter()
+            .map(|name| {
+                json!({
+                    "name": name,
+                    "url": format!("{}/{}/{}/", prefix, kind, name),
+                    "posts": to_value(&groups[name]),
+                })
+            })
+            .collect(),
+    )
+}
+
+fn string_arg(value: &Value, name: &str) -> tera::Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        _ => Err(tera::Error::msg(format!("{}: expected a string, got {}", name, value))),
+    }
+}
+
+fn required_arg(args: &HashMap<String, Value>, function: &str, key: &str) -> tera::Result<String> {
+    let value = args
+        .get(key)
+        .ok_or_else(|| tera::Error::msg(format!("{}: missing `{}` argument", function, key)))?;
+    string_arg(value, function)
+}
+
+fn optional_arg(args: &HashMap<String, Value>, function: &str, key: &str) -> tera::Result<Option<String>> {
+    args.get(key).map(|value| string_arg(value, function)).transpose()
+}
+
+fn parse_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .ok()
+        .or_else(|| DateTime::parse_from_rfc3339(date).ok().map(|d| d.date_naive()))
+}
+
+fn date_format(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let date = string_arg(value, "date_format")?;
+    let format = match args.get("format") {
+        Some(format) => string_arg(format, "date_format")?,
+        None => "%B %-d, %Y".to_string(),
+    };
+    let parsed = parse_date(&date)
+        .ok_or_else(|| tera::Error::msg(format!("date_format: `{}` is not a YYYY-MM-DD or RFC 3339 date", date)))?;
+    let mut formatted = String::new();
+    write!(formatted, "{}", parsed.format(&format))
+        .map_err(|_| tera::Error::msg(format!("date_format: `{}` is not a valid date format", format)))?;
+    Ok(Value::String(formatted))
+}
+
+fn reading_time_filter(value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+    let text = string_arg(value, "reading_time")?;
+    let words_per_minute = args.get("wpm").and_then(Value::as_u64).unwrap_or(200) as usize;
+    Ok(Value::from(reading_time(&text, words_per_minute)))
+}
+
+struct MarkdownFilter;
+
+impl Filter for MarkdownFilter {
+    fn filter(&self, value: &Value, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        let source = string_arg(value, "markdown")?;
+        let mut output = String::new();
+        html::push_html(&mut output, Parser::new_ext(&source, Options::all()));
+
+        let inline = args.get("inline").and_then(Value::as_bool).unwrap_or(false);
+        if inline {
+            let trimmed = output.trim();
+            if let Some(inner) = trimmed.strip_prefix("<p>").and_then(|s| s.strip_suffix("</p>")) {
+                if !inner.contains("<p>") {
+                    output = inner.to_string();
+                }
+            }
+        }
+        Ok(Value::String(output))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+struct AssetUrl {
+    static_dirs: Vec<PathBuf>,
+    fingerprinted: BTreeMap<String, String>,
+    hashes: Mutex<HashMap<String, String>>,
+}
+
+impl Function for AssetUrl {
+    fn call(&self, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        let path = required_arg(args, "asset_url", "path")?;
+        let relative = path.trim_start_matches('/').to_string();
+        if let Some(hashed) = self.fingerprinted.get(&relative) {
+            return Ok(Value::String(format!("/{}", hashed)));
+        }
+
+        let mut hashes = self.hashes.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(hash) = hashes.get(&relative) {
+            return Ok(Value::String(format!("/{}?h={}", relative, hash)));
+        }
+
+        let file = self
+            .static_dirs
+            .iter()
+            .map(|dir| dir.join(&relative))
+            .find(|candidate| candidate.is_file())
+            .ok_or_else(|| tera::Error::msg(format!("asset_url: `{}` not found in static files", path)))?;
+        let bytes = fs::read(&file).map_err(|e| tera::Error::chain(format!("asset_url: failed to read {:?}", file), e))?;
+        let digest = Sha256::digest(&bytes);
+        let hash: String = digest.iter().take(5).map(|b| format!("{:02x}", b)).collect();
+
+        let url = format!("/{}?h={}", relative, hash);
+        hashes.insert(relative, hash);
+        Ok(Value::String(url))
+    }
+}
+
+struct JsonLd {
+    base_url: String,
+    site_title: String,
+    site_description: Option<String>,
+}
+
+impl JsonLd {
+    fn person(&self, author: &Value) -> Value {
+        let mut person = json!({
+            "@type": "Person",
+            "name": author["name"],
+            "url": absolute_url(&self.base_url, author["url"].as_str().unwrap_or("/")),
+        });
+        if let Some(avatar) = author["avatar"].as_str() {
+            person["image"] = Value::String(match avatar.contains("://") {
+                true => avatar.to_string(),
+                false => absolute_url(&self.base_url, avatar),
+            });
+        }
+        if let Some(links) = author["links"].as_object().filter(|l| !l.is_empty()) {
+            person["sameAs"] = Value::Array(links.values().cloned().collect());
+        }
+        person
+    }
+}
+
+impl Function for JsonLd {
+    fn call(&self, args: &HashMap<String, Value>) -> tera::Result<Value> {
+        let data = match args.get("post") {
+            Some(post) => {
+                let meta = &post["meta"];
+                let url = post["url"].as_str().unwrap_or("/");
+                let mut data = json!({
+                    "@context": "https://schema.org",
+                    "@type": "BlogPosting",
+                    "headline": meta["title"],
+                    "datePublished": meta["date"],
+                    "url": absolute_url(&self.base_url, url),
+                    "mainEntityOfPage": absolute_url(&self.base_url, url),
+                    "isPartOf": {
+                        "@type": "Blog",
+                        "name": self.site_title,
+                        "url": format!("{}/", self.base_url),
+                    },
+                });
+                if let Some(image) = meta["image"].as_str() {
+                    data["image"] = Value::String(absolute_url(&self.base_url, image));
+                }
+                if let Some(tags) = meta["tags"].as_array() {
+                    let keywords: Vec<&str> = tags.iter().filter_map(Value::as_str).collect();
+                    data["keywords"] = Value::String(keywords.join(", "));
+                }
+                if let Some(authors) = post["authors"].as_array().filter(|a| !a.is_empty()) {
+                    data["author"] = Value::Array(authors.iter().map(|author| self.person(author)).collect());
+                }
+                data
+            }
+            None => json!({
+                "@context": "https://schema.org",
+                "@type": "WebSite",
+                "name": self.site_title,
+                "description": self.site_description,
+                "url": format!("{}/", self.base_url),
+            }),
+        };
+
+        let encoded = serde_json::to_string(&data).map_err(|e| tera::Error::chain("json_ld: failed to encode", e))?;
+        Ok(Value::String(format!(
+            "<script type=\"application/ld+json\">{}</script>",
+            encoded.replace("</", "<\\/")
+        )))
+    }
+
+    fn is_safe(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Author, PostMeta};
+    use tempfile::TempDir;
+
+    fn render(tera: &mut Tera, template: &str, context: &tera::Context) -> String {
+        tera.add_raw_template("test", template).expect("Failed to add template");
+        tera.render("test", context).expect("Failed to render template")
+    }
+
+    fn site_tera(static_dir: PathBuf) -> Tera {
+        let posts = vec![Post {
+            meta: PostMeta {
+                title: "Async Traits".to_string(),
+                date: "2024-03-05".to_string(),
+                slug: "async-traits".to_string(),
+                tags: Some(vec!["rust".to_string(), "async".to_string()]),
+                ..Default::default()
+            },
+            content: "<p>one two three</p>".to_string(),
+            url: "/posts/async-traits/".to_string(),
+            authors: vec![Author {
+                id: "ada".to_string(),
+                name: "Ada Lovelace".to_string(),
+                url: "/authors/ada/".to_string(),
+                avatar: Some("/img/ada.png".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }];
+        let config = Config {
+            title: "Test Blog".to_string(),
+            base_url: "https://example.com/".to_string(),
+            ..Default::default()
+        };
+
+        let mut tera = Tera::default();
+        register_filters(&mut tera);
+        let mut fingerprinted = BTreeMap::new();
+        fingerprinted.insert("js/app.js".to_string(), "js/app.0a1b2c3d.js".to_string());
+        register_site_functions(&mut tera, &posts, &config, vec![static_dir], fingerprinted);
+        tera
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut tera = Tera::default();
+        register_filters(&mut tera);
+        let mut context = tera::Context::new();
+        context.insert("date", "2024-03-05");
+        context.insert("title", "Hello, World: Rust & Tera!");
+        context.insert("body", "<p>word </p>".repeat(450).as_str());
+
+        assert_eq!(render(&mut tera, "{{ date | date_format }}", &context), "March 5, 2024");
+        assert_eq!(render(&mut tera, "{{ date | date_format(format=\"%d/%m/%Y\") }}", &context), "05/03/2024");
+        tera.add_raw_template("invalid", "{{ date | date_format(format=\"%Q\") }}").unwrap();
+        let err = tera.render("invalid", &context).unwrap_err();
+        assert!(format!("{:?}", err).contains("`%Q` is not a valid date format"), "{:?}", err);
+        assert_eq!(render(&mut tera, "{{ title | slugify }}", &context), "hello-world-rust-tera");
+        assert_eq!(render(&mut tera, "{{ body | reading_time }}", &context), "3");
+        assert_eq!(render(&mut tera, "{{ body | reading_time(wpm=450) }}", &context), "1");
+        assert_eq!(render(&mut tera, "{{ \"*hi*\" | markdown(inline=true) }}", &context), "<em>hi</em>");
+        assert_eq!(render(&mut tera, "{{ \"# Title\" | markdown }}", &context), "<h1>Title</h1>\n");
+    }
+
+    #[test]
+    fn test_site_functions() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        fs::create_dir_all(temp_dir.path().join("css")).unwrap();
+        fs::write(temp_dir.path().join("css/style.css"), "body {}").unwrap();
+        let mut tera = site_tera(temp_dir.path().to_path_buf());
+        let context = tera::Context::new();
+
+        assert_eq!(render(&mut tera, "{{ get_url(path=\"/tags/rust/\") }}", &context), "https://example.com/tags/rust/");
+        assert_eq!(
+            render(&mut tera, "{% set p = get_post(slug=\"async-traits\") %}{{ p.meta.title }} {{ p.url }}", &context),
+            "Async Traits /posts/async-traits/"
+        );
+        assert_eq!(
+            render(&mut tera, "{% for t in get_taxonomy(kind=\"tags\") %}{{ t.name }}:{{ t.posts | length }} {% endfor %}", &context),
+            "async:1 rust:1 "
+        );
+
+        let asset = render(&mut tera, "{{ asset_url(path=\"css/style.css\") }}", &context);
+        assert!(asset.starts_with("/css/style.css?h="), "{}", asset);
+        assert_eq!(asset.len(), "/css/style.css?h=".len() + 10);
+        assert_eq!(render(&mut tera, "{{ asset_url(path=\"/js/app.js\") }}", &context), "/js/app.0a1b2c3d.js");
+
+        tera.add_raw_template("missing.html", "{{ get_post(slug=\"nope\") }}").unwrap();
+        assert!(tera.render("missing.html", &context).is_err());
+    }
+
+    #[test]
+    fn test_json_ld() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let mut tera = site_tera(temp_dir.path().to_path_buf());
+        let context = tera::Context::new();
+
+        let output = render(&mut tera, "{{ json_ld(post=get_post(slug=\"async-traits\")) }}", &context);
+        assert!(output.starts_with("<script type=\"application/ld+json\">"), "{}", output);
+        assert!(output.contains("\"@type\":\"BlogPosting\""));
+        assert!(output.contains("\"headline\":\"Async Traits\""));
+        assert!(output.contains("\"url\":\"https://example.com/posts/async-traits/\""));
+        assert!(output.contains("\"keywords\":\"rust, async\""));
+        assert!(
+            output.contains("\"author\":[{\"@type\":\"Person\",\"image\":\"https://example.com/img/ada.png\",\"name\":\"Ada Lovelace\",\"url\":\"https://example.com/authors/ada/\"}]"),
+            "{}",
+            output
+        );
+
+        let output = render(&mut tera, "{{ json_ld() }}", &context);
+        assert!(output.contains("\"@type\":\"WebSite\""));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::domain::{Post, PostLink};
+use crate::error::Result;
+use crate::html::{decode_entities, resolve_local_url, tokenize, Token};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Serialize)]
+struct Graph<'a> {
+    nodes: Vec<Node<'a>>,
+    edges: Vec<Edge<'a>>,
+}
+
+#[derive(Serialize)]
+struct Node<'a> {
+    id: &'a str,
+    title: &'a str,
+    url: &'a str,
+    section: Option<&'a str>,
+    tags: &'a [String],
+}
+
+#[derive(Serialize)]
+struct Edge<'a> {
+    source: &'a str,
+    target: &'a str,
+}
+
+pub fn link_posts(posts: &mut [Post], base_url: &str) {
+    let by_path: HashMap<String, usize> = posts
+        .iter()
+        .enumerate()
+        .map(|(i, post)| (post.url.trim_matches('/').to_string(), i))
+        .collect();
+
+    let outgoing: Vec<Vec<usize>> = posts
+        .par_iter()
+        .enumerate()
+        .map(|(source, post)| {
+            let base_dir = post.url.trim_matches('/');
+            let mut targets = Vec::new();
+            for token in tokenize(&post.content) {
+                let Token::StartTag(tag, _) = token else {
+                    continue;
+                };
+                let Some(href) = tag.attr("href").map(decode_entities) else {
+                    continue;
+                };
+                let Some(local) = resolve_local_url(&href, base_dir, base_url) else {
+                    continue;
+                };
+                let path = local.path.trim_end_matches("index.html").trim_matches('/');
+                if let Some(&target) = by_path.get(path) {
+                    if target != source && !targets.contains(&target) {
+                        targets.push(target);
+                    }
+                }
+            }
+            targets
+        })
+        .collect();
+
+    let mut incoming = vec![Vec::new(); posts.len()];
+    for (source, targets) in outgoing.iter().enumerate() {
+        for &target in targets {
+            incoming[target].push(source);
+        }
+    }
+
+    let summaries: Vec<PostLink> = posts.iter().map(PostLink::of).collect();
+    for (i, post) in posts.iter_mut().enumerate() {
+        post.links = outgoing[i].iter().map(|&t| summaries[t].clone()).collect();
+        post.backlinks = incoming[i].iter().map(|&s| summaries[s].clone()).collect();
+    }
+}
+
+pub fn generate_graph(posts: &[Post]) -> Result<String> {
+    let graph = Graph {
+        nodes: posts
+            .iter()
+            .map(|post| Node {
+                id: &post.url,
+                title: &post.meta.title,
+                url: &post.url,
+                section: post.section.as_deref(),
+                tags: post.meta.tags.as_deref().unwrap_or_default(),
+            })
+            .collect(),
+        edges: posts
+            .iter()
+            .flat_map(|post| {
+                post.links.iter().map(|link| Edge {
+                    source: &post.url,
+                    target: &link.url,
+                })
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_string(&graph)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(slug: &str, content: &str) -> Post {
+        Post {
+            content: content.to_string(),
+            ..Post::fixture(slug)
+        }
+    }
+
+    #[test]
+    fn test_link_posts() {
+        let mut posts = vec![
+            post("a", r#"<a href="/posts/b/">B</a> <a href="../c/#part">C</a> <a href="/posts/b/index.html">B again</a> <a href="/posts/a/">Self</a>"#),
+            post("b", r#"<a href="https://example.com/posts/c">C</a> <a href="https://other.org/posts/a/">Elsewhere</a>"#),
+            post("c", r#"<a href="/tags/rust/">Rust</a>"#),
+        ];
+        link_posts(&mut posts, "https://example.com");
+
+        let slugs = |links: &[PostLink]| links.iter().map(|l| l.slug.clone()).collect::<Vec<_>>();
+        assert_eq!(slugs(&posts[0].links), vec!["b", "c"]);
+        assert_eq!(slugs(&posts[0].backlinks), Vec::<String>::new());
+        assert_eq!(slugs(&posts[1].backlinks), vec!["a"]);
+        assert_eq!(slugs(&posts[2].backlinks), vec!["a", "b"]);
+        assert_eq!(posts[2].backlinks[0].title, "A");
+        assert_eq!(posts[2].backlinks[0].url, "/posts/a/");
+
+        let graph: serde_json::Value = serde_json::from_str(&generate_graph(&posts).unwrap()).unwrap();
+        assert_eq!(graph["nodes"].as_array().unwrap().len(), 3);
+        assert_eq!(graph["nodes"][1]["id"], "/posts/b/");
+        assert_eq!(
+            graph["edges"],
+            serde_json::json!([
+                { "source": "/posts/a/", "target": "/posts/b/" },
+                { "source": "/posts/a/", "target": "/posts/c/" },
+                { "source": "/posts/b/", "target": "/posts/c/" },
+            ])
+        );
+    }
+}
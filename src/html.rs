@@ -0,0 +1,347 @@
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tag {
+    pub name: String,
+    pub attrs: Vec<(String, Option<String>)>,
+    pub self_closing: bool,
+}
+
+impl Tag {
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    pub fn has_attr(&self, name: &str) -> bool {
+        self.attrs.iter().any(|(key, _)| key.eq_ignore_ascii_case(name))
+    }
+
+    pub fn set_attr(&mut self, name: &str, value: &str) {
+        match self.attrs.iter_mut().find(|(key, _)| key.eq_ignore_ascii_case(name)) {
+            Some((_, existing)) => *existing = Some(value.to_string()),
+            None => self.attrs.push((name.to_string(), Some(value.to_string()))),
+        }
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = format!("<{}", self.name);
+        for (key, value) in &self.attrs {
+            html.push(' ');
+            html.push_str(key);
+            if let Some(value) = value {
+                html.push_str("=\"");
+                html.push_str(&value.replace('"', "&quot;"));
+                html.push('"');
+            }
+        }
+        html.push_str(if self.self_closing { " />" } else { ">" });
+        html
+    }
+}
+
+pub enum Token<'a> {
+    Text(&'a str),
+    Comment(&'a str),
+    StartTag(Tag, &'a str),
+    EndTag(String, &'a str),
+    Other(&'a str),
+}
+
+pub fn tokenize(html: &str) -> Vec<Token<'_>> {
+    let bytes = html.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    let mut text_start = 0;
+
+    while pos < bytes.len() {
+        if bytes[pos] != b'<' {
+            pos += 1;
+            continue;
+        }
+
+        let rest = &html[pos..];
+        let (token, end) = if rest.starts_with("<!--") {
+            let end = rest.find("-->").map_or(html.len(), |i| pos + i + 3);
+            (Token::Comment(&html[pos..end]), end)
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            let end = rest.find('>').map_or(html.len(), |i| pos + i + 1);
+            (Token::Other(&html[pos..end]), end)
+        } else if rest.starts_with("</") {
+            let end = rest.find('>').map_or(html.len(), |i| pos + i + 1);
+            let name = html[pos + 2..end.saturating_sub(1).max(pos + 2)].trim().to_ascii_lowercase();
+            (Token::EndTag(name, &html[pos..end]), end)
+        } else if rest[1..].starts_with(|c: char| c.is_ascii_alphabetic()) {
+            match parse_start_tag(html, pos) {
+                Some((tag, end)) => (Token::StartTag(tag, &html[pos..end]), end),
+                None => {
+                    pos += 1;
+                    continue;
+                }
+            }
+        } else {
+            pos += 1;
+            continue;
+        };
+
+        if text_start < pos {
+            tokens.push(Token::Text(&html[text_start..pos]));
+        }
+
+        let raw_text_element = match &token {
+            Token::StartTag(tag, _) if !tag.self_closing && is_raw_text(&tag.name) => Some(tag.name.clone()),
+            _ => None,
+        };
+        tokens.push(token);
+        pos = end;
+
+        if let Some(name) = raw_text_element {
+            let close = format!("</{}", name);
+            let body_end = find_ignore_case(&html[pos..], &close).map_or(html.len(), |i| pos + i);
+            if body_end > pos {
+                tokens.push(Token::Text(&html[pos..body_end]));
+            }
+            pos = body_end;
+        }
+        text_start = pos;
+    }
+
+    if text_start < html.len() {
+        tokens.push(Token::Text(&html[text_start..]));
+    }
+    tokens
+}
+
+pub fn is_raw_text(name: &str) -> bool {
+    matches!(name, "script" | "style" | "textarea" | "title")
+}
+
+pub fn rewrite_tags(html: &str, mut rewrite: impl FnMut(&mut Tag)) -> String {
+    let mut output = String::with_capacity(html.len());
+    for token in tokenize(html) {
+        match token {
+            Token::StartTag(mut tag, raw) => {
+                let original = tag.clone();
+                rewrite(&mut tag);
+                if tag == original {
+                    output.push_str(raw);
+                } else {
+                    output.push_str(&tag.to_html());
+                }
+            }
+            Token::Text(raw) | Token::Comment(raw) | Token::Other(raw) | Token::EndTag(_, raw) => output.push_str(raw),
+        }
+    }
+    output
+}
+
+pub fn decode_entities(value: &str) -> String {
+    let mut decoded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let character = entity.and_then(|(name, _)| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => name.strip_prefix('#').and_then(|dec| dec.parse().ok()).and_then(char::from_u32),
+            },
+        });
+        match (character, entity) {
+            (Some(character), Some((_, end))) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+pub struct LocalUrl {
+    pub path: String,
+    pub query: String,
+    pub fragment: String,
+}
+
+pub fn resolve_local_url(url: &str, base_dir: &str, base_url: &str) -> Option<LocalUrl> {
+    let url = url.trim();
+    let (without_fragment, fragment) = match url.split_once('#') {
+        Some((before, after)) => (before, format!("#{}", after)),
+        None => (url, String::new()),
+    };
+    let (path, query) = match without_fragment.split_once('?') {
+        Some((before, after)) => (before, format!("?{}", after)),
+        None => (without_fragment, String::new()),
+    };
+
+    let base_url = base_url.trim_end_matches('/');
+    let path = if !base_url.is_empty() && path.starts_with(base_url) {
+        let rest = &path[base_url.len()..];
+        if !rest.is_empty() && !rest.starts_with('/') {
+            return None;
+        }
+        format!("/{}", rest.trim_start_matches('/'))
+    } else {
+        path.to_string()
+    };
+
+    if path.starts_with("//") || path.split('/').next().is_some_and(|first| first.contains(':')) {
+        return None;
+    }
+
+    let mut segments: Vec<&str> = if path.starts_with('/') {
+        Vec::new()
+    } else {
+        base_dir.split('/').filter(|s| !s.is_empty()).collect()
+    };
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    let mut resolved = segments.join("/");
+    if path.ends_with('/') && !resolved.is_empty() {
+        resolved.push('/');
+    }
+
+    Some(LocalUrl {
+        path: resolved,
+        query,
+        fragment,
+    })
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn parse_start_tag(html: &str, start: usize) -> Option<(Tag, usize)> {
+    let bytes = html.as_bytes();
+    let mut pos = start + 1;
+    while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'>' && bytes[pos] != b'/' {
+        pos += 1;
+    }
+    let name = html[start + 1..pos].to_ascii_lowercase();
+    let mut attrs = Vec::new();
+    let mut self_closing = false;
+
+    loop {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos >= bytes.len() {
+            return None;
+        }
+        match bytes[pos] {
+            b'>' => return Some((Tag { name, attrs, self_closing }, pos + 1)),
+            b'/' => {
+                self_closing = true;
+                pos += 1;
+                continue;
+            }
+            _ => {}
+        }
+        self_closing = false;
+
+        let key_start = pos;
+        while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && !matches!(bytes[pos], b'=' | b'>' | b'/') {
+            pos += 1;
+        }
+        let key = html[key_start..pos].to_string();
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+
+        if pos < bytes.len() && bytes[pos] == b'=' {
+            pos += 1;
+            while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+                pos += 1;
+            }
+            let value = if pos < bytes.len() && (bytes[pos] == b'"' || bytes[pos] == b'\'') {
+                let quote = bytes[pos];
+                let value_start = pos + 1;
+                let value_end = value_start + html[value_start..].find(quote as char)?;
+                pos = value_end + 1;
+                &html[value_start..value_end]
+            } else {
+                let value_start = pos;
+                while pos < bytes.len() && !bytes[pos].is_ascii_whitespace() && bytes[pos] != b'>' {
+                    pos += 1;
+                }
+                &html[value_start..pos]
+            };
+            attrs.push((key, Some(value.to_string())));
+        } else if !key.is_empty() {
+            attrs.push((key, None));
+        } else {
+            pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_tags() {
+        let html = r#"<!DOCTYPE html><link rel=stylesheet href="/a.css"><script>if (a<b) { x = "<img src=x>"; }</script><img src='b.png' alt="x &quot;y&quot;" hidden><p>text</p>"#;
+        let output = rewrite_tags(html, |tag| {
+            if tag.name == "img" {
+                tag.set_attr("loading", "lazy");
+            }
+        });
+        assert_eq!(
+            output,
+            r#"<!DOCTYPE html><link rel=stylesheet href="/a.css"><script>if (a<b) { x = "<img src=x>"; }</script><img src="b.png" alt="x &quot;y&quot;" hidden loading="lazy"><p>text</p>"#
+        );
+    }
+
+    #[test]
+    fn test_resolve_local_url() {
+        let resolve = |url: &str| resolve_local_url(url, "posts/hello", "https://example.com").map(|u| (u.path, u.query, u.fragment));
+        assert_eq!(resolve("/css/a.css?h=1"), Some(("css/a.css".to_string(), "?h=1".to_string(), String::new())));
+        assert_eq!(resolve("../img/x.png#top"), Some(("posts/img/x.png".to_string(), String::new(), "#top".to_string())));
+        assert_eq!(resolve("https://example.com/tags/rust/"), Some(("tags/rust/".to_string(), String::new(), String::new())));
+        assert_eq!(resolve("#section"), Some(("posts/hello".to_string(), String::new(), "#section".to_string())));
+        assert!(resolve("https://other.com/x").is_none());
+        assert!(resolve("https://example.com.evil/x").is_none());
+        assert!(resolve("mailto:me@example.com").is_none());
+        assert!(resolve("//cdn.example.com/x.js").is_none());
+    }
+
+    #[test]
+    fn test_tokenize_attributes() {
+        let tokens = tokenize(r#"<a href="/x/" data-id=3 download>link</a><br/>"#);
+        let tags: Vec<&Tag> = tokens
+            .iter()
+            .filter_map(|t| match t {
+                Token::StartTag(tag, _) => Some(tag),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(tags[0].attr("href"), Some("/x/"));
+        assert_eq!(tags[0].attr("data-id"), Some("3"));
+        assert!(tags[0].has_attr("download"));
+        assert_eq!(tags[1].name, "br");
+        assert!(tags[1].self_closing);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::domain::{Config, Post, PostMeta, Translation};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub fn language_suffix<'a>(file_name: &'a str, config: &Config) -> Option<(&'a str, &'a str)> {
+    let (name, lang) = file_name.strip_suffix(".md")?.rsplit_once('.')?;
+    config.language_codes().contains(&lang).then_some((name, lang))
+}
+
+pub fn language_of(path: &Path, meta: &PostMeta, config: &Config) -> String {
+    let from_name = || {
+        let name = path.file_name()?.to_str()?;
+        language_suffix(name, config).map(|(_, lang)| lang.to_string())
+    };
+    meta.lang
+        .clone()
+        .or_else(from_name)
+        .unwrap_or_else(|| config.default_language().to_string())
+}
+
+pub fn is_bundle_index(file_name: &str, config: &Config) -> bool {
+    file_name == "index.md" || language_suffix(file_name, config).is_some_and(|(name, _)| name == "index")
+}
+
+pub fn sort_by_language(posts: &mut [Post], config: &Config) {
+    let codes = config.language_codes();
+    posts.sort_by_key(|post| codes.iter().position(|code| *code == config.language(&post.lang).code).unwrap_or(codes.len()));
+}
+
+pub fn language_groups<'a>(posts: &'a [Post], config: &Config) -> Vec<(String, &'a [Post])> {
+    let mut groups: Vec<(String, &[Post])> = posts
+        .chunk_by(|a, b| a.lang == b.lang)
+        .map(|chunk| (config.language(&chunk[0].lang).code, chunk))
+        .collect();
+    if groups.first().is_none_or(|(lang, _)| lang != config.default_language()) {
+        groups.insert(0, (config.default_language().to_string(), &[]));
+    }
+    groups
+}
+
+pub fn link_translations(posts: &mut [Post], config: &Config) {
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, post) in posts.iter().enumerate() {
+        if let Some(key) = translation_key(post, config) {
+            groups.entry(key).or_default().push(i);
+        }
+    }
+
+    let summaries: Vec<Translation> = posts
+        .iter()
+        .map(|post| Translation {
+            lang: post.lang.clone(),
+            slug: post.meta.slug.clone(),
+            title: post.meta.title.clone(),
+            url: post.url.clone(),
+        })
+        .collect();
+    for post in posts.iter_mut() {
+        post.translations.clear();
+    }
+    for group in groups.values() {
+        for &i in group {
+            let mut translations: Vec<Translation> = Vec::new();
+            for &j in group {
+                if summaries[j].lang != summaries[i].lang && !translations.iter().any(|t| t.lang == summaries[j].lang) {
+                    translations.push(summaries[j].clone());
+                }
+            }
+            posts[i].translations = translations;
+        }
+    }
+}
+
+fn translation_key(post: &Post, config: &Config) -> Option<String> {
+    if let Some(key) = &post.meta.translation_key {
+        return Some(format!("key:{}", key));
+    }
+    let path = post.source_path.as_ref()?;
+    let name = path.file_name()?.to_str()?;
+    let name = match language_suffix(name, config) {
+        Some((name, _)) => format!("{}.md", name),
+        None => name.to_string(),
+    };
+    Some(format!("path:{}", path.with_file_name(name).display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::LanguageConfig;
+    use std::path::PathBuf;
+
+    fn config() -> Config {
+        let mut config = Config {
+            title: "Blog".to_string(),
+            ..Default::default()
+        };
+        config.languages.insert("es".to_string(), LanguageConfig::default());
+        config
+    }
+
+    fn post(path: &str, lang: &str, key: Option<&str>) -> Post {
+        Post {
+            meta: PostMeta {
+                slug: path.to_string(),
+                translation_key: key.map(str::to_string),
+                ..Default::default()
+            },
+            lang: lang.to_string(),
+            url: format!("/{}/", path),
+            source_path: Some(PathBuf::from(format!("content/posts/{}", path))),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_language_detection() {
+        let config = config();
+        let meta = PostMeta::default();
+        assert_eq!(language_of(Path::new("posts/hello.es.md"), &meta, &config), "es");
+        assert_eq!(language_of(Path::new("posts/hello.en.md"), &meta, &config), "en");
+        assert_eq!(language_of(Path::new("posts/v1.2.md"), &meta, &config), "en");
+        let meta = PostMeta {
+            lang: Some("fr".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(language_of(Path::new("posts/hello.es.md"), &meta, &config), "fr");
+
+        assert!(is_bundle_index("index.md", &config));
+        assert!(is_bundle_index("index.es.md", &config));
+        assert!(!is_bundle_index("index.fr.md", &config));
+        assert_eq!(config.language_prefix("es"), "/es");
+        assert_eq!(config.language_prefix("en"), "");
+        assert_eq!(config.language("").url, "/");
+    }
+
+    #[test]
+    fn test_link_translations() {
+        let config = config();
+        let mut posts = vec![
+            post("hola.es.md", "es", Some("greeting")),
+            post("hello.md", "en", None),
+            post("hello.es.md", "es", None),
+            post("welcome.md", "en", Some("greeting")),
+            post("alone.md", "en", None),
+        ];
+        sort_by_language(&mut posts, &config);
+        link_translations(&mut posts, &config);
+
+        let slugs: Vec<&str> = posts.iter().map(|p| p.meta.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["hello.md", "welcome.md", "alone.md", "hola.es.md", "hello.es.md"]);
+        let translations = |i: usize| posts[i].translations.iter().map(|t| t.slug.as_str()).collect::<Vec<_>>();
+        assert_eq!(translations(0), vec!["hello.es.md"]);
+        assert_eq!(translations(1), vec!["hola.es.md"]);
+        assert!(translations(2).is_empty());
+        assert_eq!(translations(4), vec!["hello.md"]);
+        assert_eq!(posts[4].translations[0].lang, "en");
+
+        let groups: Vec<(String, usize)> = language_groups(&posts, &config).into_iter().map(|(lang, p)| (lang, p.len())).collect();
+        assert_eq!(groups, vec![("en".to_string(), 3), ("es".to_string(), 2)]);
+        let groups = language_groups(&posts[3..], &config);
+        assert_eq!((groups[0].0.as_str(), groups[0].1.len()), ("en", 0));
+    }
+}
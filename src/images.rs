@@ -0,0 +1,474 @@
+use crate::domain::ImagesConfig;
+use crate::html::{resolve_local_url, tokenize, Token};
+use crate::error::{BlogError, IoResultExt, Result};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::FilterType;
+use image::error::{EncodingError, ImageFormatHint};
+use image::{DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+const DEFAULT_WIDTHS: &[u32] = &[480, 960, 1600];
+const DEFAULT_QUALITY: u8 = 80;
+const SUPPORTED_FORMATS: &[&str] = &["webp"];
+const CACHE_VERSION: u32 = 2;
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+pub struct ProcessedImage {
+    pub src: String,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub srcset: String,
+    pub sources: Vec<ImageSource>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageSource {
+    pub mime_type: String,
+    pub srcset: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedImage {
+    variants: Vec<Variant>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Variant {
+    format: String,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, Default)]
+struct Fingerprinted {
+    files: BTreeMap<String, String>,
+    generated: Vec<String>,
+}
+
+/// The result for one path, filled in by whichever caller gets to it first.
+type Slot = Arc<Mutex<Option<Option<ProcessedImage>>>>;
+
+pub struct ImageProcessor {
+    output_dir: PathBuf,
+    cache_dir: PathBuf,
+    enabled: bool,
+    widths: Vec<u32>,
+    formats: Vec<String>,
+    quality: u8,
+    processed: Mutex<HashMap<String, Slot>>,
+    fingerprinted: Mutex<Option<Fingerprinted>>,
+}
+
+impl ImageProcessor {
+    pub fn new(output_dir: &Path, cache_dir: &Path, config: &ImagesConfig) -> Result<Self> {
+        let formats = config.formats.clone().unwrap_or_else(|| vec!["webp".to_string()]);
+        for format in &formats {
+            if !SUPPORTED_FORMATS.contains(&format.as_str()) {
+                return Err(BlogError::InvalidConfig(format!(
+                    "Image format `{}` is not supported, expected one of: {}",
+                    format,
+                    SUPPORTED_FORMATS.join(", ")
+                )));
+            }
+        }
+
+        let mut widths = config.widths.clone().unwrap_or_else(|| DEFAULT_WIDTHS.to_vec());
+        widths.retain(|w| *w > 0);
+        widths.sort_unstable();
+        widths.dedup();
+
+        Ok(Self {
+            output_dir: output_dir.to_path_buf(),
+            cache_dir: cache_dir.to_path_buf(),
+            enabled: config.enabled.unwrap_or(true),
+            widths,
+            formats,
+            quality: config.quality.unwrap_or(DEFAULT_QUALITY).clamp(1, 100),
+            processed: Mutex::new(HashMap::new()),
+            fingerprinted: Mutex::new(None),
+        })
+    }
+
+    /// Tells the processor that the output was fingerprinted: sources are looked up by their hashed
+    /// names, and files written from now on are collected by `take_generated` for a second pass.
+    pub fn set_fingerprinted(&self, files: BTreeMap<String, String>) {
+        *self.fingerprinted.lock().unwrap_or_else(PoisonError::into_inner) = Some(Fingerprinted {
+            files,
+            generated: Vec::new(),
+        });
+    }
+
+    pub fn take_generated(&self) -> Vec<String> {
+        let mut fingerprinted = self.fingerprinted.lock().unwrap_or_else(PoisonError::into_inner);
+        fingerprinted.as_mut().map(|f| std::mem::take(&mut f.generated)).unwrap_or_default()
+    }
+
+    fn generated(&self, relative: &str) {
+        if let Some(fingerprinted) = self.fingerprinted.lock().unwrap_or_else(PoisonError::into_inner).as_mut() {
+            fingerprinted.generated.push(relative.to_string());
+        }
+    }
+
+    pub fn process(&self, path: &str) -> Result<Option<ProcessedImage>> {
+        let path = path.trim_start_matches('/').to_string();
+        // Claim a slot per path so posts sharing an image wait for one encoding instead of racing.
+        let slot = Arc::clone(self.processed.lock().unwrap_or_else(PoisonError::into_inner).entry(path.clone()).or_default());
+        let mut slot = slot.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(processed) = slot.as_ref() {
+            return Ok(processed.clone());
+        }
+
+        let processed = self.process_uncached(&path)?;
+        *slot = Some(processed.clone());
+        Ok(processed)
+    }
+
+    fn process_uncached(&self, path: &str) -> Result<Option<ProcessedImage>> {
+        let fallback_format = match extension(path).to_ascii_lowercase().as_str() {
+            "jpg" | "jpeg" => "jpg",
+            "png" => "png",
+            "webp" => "webp",
+            _ => return Ok(None),
+        };
+        let hashed = self.fingerprinted.lock().unwrap_or_else(PoisonError::into_inner).as_ref().and_then(|f| f.files.get(path).cloned());
+        let source = self.output_dir.join(hashed.as_deref().unwrap_or(path));
+        if !self.enabled || !source.is_file() {
+            return Ok(None);
+        }
+
+        let bytes = fs::read(&source).io("read image", &source)?;
+        let key = self.cache_key(&bytes);
+        let cache_index = self.cache_dir.join(format!("{}.json", key));
+
+        let cached = match fs::read_to_string(&cache_index).ok().and_then(|json| serde_json::from_str::<CachedImage>(&json).ok()) {
+            Some(cached) if cached.variants.iter().all(|v| self.cache_file(&key, v).exists()) => cached,
+            _ => {
+                let cached = self.encode_variants(&source, &bytes, &key, fallback_format)?;
+                write_atomic(&cache_index, serde_json::to_string(&cached)?.as_bytes())?;
+                cached
+            }
+        };
+
+        let stem = path.rsplit_once('.').map_or(path, |(stem, _)| stem);
+        let mut srcsets: Vec<(String, Vec<String>)> = Vec::new();
+        let mut largest = None;
+        let mut original = None;
+        for variant in &cached.variants {
+            let relative = format!("{}-{}w.{}", stem, variant.width, variant.format);
+            let cache_file = self.cache_file(&key, variant);
+            fs::copy(&cache_file, self.output_dir.join(&relative)).io("copy cached image", &cache_file)?;
+            self.generated(&relative);
+
+            let candidate = format!("/{} {}w", relative, variant.width);
+            match srcsets.iter_mut().find(|(format, _)| *format == variant.format) {
+                Some((_, candidates)) => candidates.push(candidate),
+                None => srcsets.push((variant.format.clone(), vec![candidate])),
+            }
+            if variant.format == fallback_format {
+                largest = Some((format!("/{}", relative), variant.width, variant.height));
+                original = Some(cache_file);
+            }
+        }
+
+        // Replace the original with its re-encoded copy so its EXIF data is not published either.
+        if let Some(original) = original {
+            fs::copy(&original, self.output_dir.join(path)).io("copy cached image", &original)?;
+            self.generated(path);
+        }
+
+        let (src, width, height) = largest.ok_or_else(|| BlogError::Asset {
+            path: source.clone(),
+            message: format!("No {} variant was generated for {:?}", fallback_format, source),
+        })?;
+        let mut processed = ProcessedImage {
+            src,
+            width: Some(width),
+            height: Some(height),
+            ..Default::default()
+        };
+        for (format, candidates) in srcsets {
+            if format == fallback_format {
+                processed.srcset = candidates.join(", ");
+            } else {
+                processed.sources.push(ImageSource {
+                    mime_type: mime_type(&format).to_string(),
+                    srcset: candidates.join(", "),
+                });
+            }
+        }
+        Ok(Some(processed))
+    }
+
+    fn cache_key(&self, bytes: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        hasher.update(format!("{}|{:?}|{:?}|{}", CACHE_VERSION, self.widths, self.formats, self.quality));
+        hasher.finalize().iter().take(16).map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn cache_file(&self, key: &str, variant: &Variant) -> PathBuf {
+        self.cache_dir.join(format!("{}-{}.{}", key, variant.width, variant.format))
+    }
+
+    fn encode_variants(&self, source: &Path, bytes: &[u8], key: &str, fallback_format: &str) -> Result<CachedImage> {
+        let image_error = |e| BlogError::Image {
+            path: source.to_path_buf(),
+            source: e,
+        };
+        let mut decoder = ImageReader::new(Cursor::new(bytes))
+            .with_guessed_format()
+            .io("read image", source)?
+            .into_decoder()
+            .map_err(image_error)?;
+        let orientation = decoder.orientation().map_err(image_error)?;
+        let mut original = DynamicImage::from_decoder(decoder).map_err(image_error)?;
+        original.apply_orientation(orientation);
+
+        let mut widths: Vec<u32> = self.widths.iter().copied().filter(|w| *w < original.width()).collect();
+        widths.push(original.width());
+
+        let mut formats = vec![fallback_format.to_string()];
+        formats.extend(self.formats.iter().filter(|f| *f != fallback_format).cloned());
+
+        fs::create_dir_all(&self.cache_dir).io("create", &self.cache_dir)?;
+        let mut variants = Vec::new();
+        let mut fallback_size = 0;
+        for format in &formats {
+            let mut encoded = Vec::new();
+            for width in &widths {
+                let resized = if *width == original.width() {
+                    original.clone()
+                } else {
+                    original.resize(*width, u32::MAX, FilterType::Lanczos3)
+                };
+                let data = self.encode(&resized, format).map_err(image_error)?;
+                encoded.push((
+                    Variant {
+                        format: format.clone(),
+                        width: resized.width(),
+                        height: resized.height(),
+                    },
+                    data,
+                ));
+            }
+
+            let size: usize = encoded.iter().map(|(_, data)| data.len()).sum();
+            if format == fallback_format {
+                fallback_size = size;
+            } else if size >= fallback_size {
+                tracing::debug!("Skipping {} variants, they are not smaller than the original format", format);
+                continue;
+            }
+            for (variant, data) in encoded {
+                let cache_file = self.cache_file(key, &variant);
+                write_atomic(&cache_file, &data)?;
+                variants.push(variant);
+            }
+        }
+
+        Ok(CachedImage { variants })
+    }
+
+    fn encode(&self, image: &DynamicImage, format: &str) -> image::ImageResult<Vec<u8>> {
+        let mut data = Vec::new();
+        match format {
+            "jpg" => DynamicImage::ImageRgb8(image.to_rgb8())
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut data, self.quality))?,
+            "png" => image.write_with_encoder(PngEncoder::new(&mut data))?,
+            "webp" => {
+                let (width, height) = (image.width(), image.height());
+                let encoded = if image.color().has_alpha() {
+                    webp::Encoder::from_rgba(&image.to_rgba8(), width, height).encode_simple(false, f32::from(self.quality))
+                } else {
+                    webp::Encoder::from_rgb(&image.to_rgb8(), width, height).encode_simple(false, f32::from(self.quality))
+                };
+                let encoded = encoded.map_err(|e| {
+                    ImageError::Encoding(EncodingError::new(ImageFormatHint::Exact(ImageFormat::WebP), format!("{:?}", e)))
+                })?;
+                data.extend_from_slice(&encoded);
+            }
+            _ => unreachable!("image formats are validated in ImageProcessor::new"),
+        }
+        Ok(data)
+    }
+
+    pub fn rewrite_html(&self, html: &str, base_dir: &str, base_url: &str) -> Result<String> {
+        let mut output = String::with_capacity(html.len());
+        for token in tokenize(html) {
+            match token {
+                Token::StartTag(mut tag, raw) if tag.name == "img" => {
+                    let processed = match tag.attr("src").and_then(|src| resolve_local_url(src, base_dir, base_url)) {
+                        Some(local) if !tag.has_attr("srcset") => self.process(&local.path)?,
+                        _ => None,
+                    };
+                    let Some(processed) = processed else {
+                        output.push_str(raw);
+                        continue;
+                    };
+
+                    tag.set_attr("src", &processed.src);
+                    tag.set_attr("srcset", &processed.srcset);
+                    if let (Some(width), Some(height)) = (processed.width, processed.height) {
+                        tag.set_attr("width", &width.to_string());
+                        tag.set_attr("height", &height.to_string());
+                    }
+                    if !tag.has_attr("loading") {
+                        tag.set_attr("loading", "lazy");
+                    }
+
+                    output.push_str("<picture>");
+                    for source in &processed.sources {
+                        output.push_str(&format!("<source type=\"{}\" srcset=\"{}\">", source.mime_type, source.srcset));
+                    }
+                    output.push_str(&tag.to_html());
+                    output.push_str("</picture>");
+                }
+                Token::StartTag(_, raw) | Token::Text(raw) | Token::Comment(raw) | Token::Other(raw) | Token::EndTag(_, raw) => {
+                    output.push_str(raw)
+                }
+            }
+        }
+        Ok(output)
+    }
+}
+
+/// Writes through a temporary file, so a cache entry shared by identical images is never read half-written.
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.{}.{}.tmp", name, std::process::id(), COUNTER.fetch_add(1, Ordering::Relaxed)));
+    fs::write(&temp, data).io("write image cache", &temp)?;
+    fs::rename(&temp, path).io("write image cache", path)
+}
+
+fn extension(path: &str) -> &str {
+    Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("")
+}
+
+fn mime_type(format: &str) -> &'static str {
+    match format {
+        "jpg" => "image/jpeg",
+        "png" => "image/png",
+        _ => "image/webp",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+    use tempfile::TempDir;
+
+    fn setup(config: &ImagesConfig) -> (TempDir, ImageProcessor) {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let output = temp_dir.path().join("public");
+        fs::create_dir_all(output.join("images")).unwrap();
+
+        let photo = RgbImage::from_fn(1200, 600, |x, y| image::Rgb([(x % 256) as u8, (y % 256) as u8, ((x * y) % 256) as u8]));
+        photo.save(output.join("images/photo.jpg")).unwrap();
+        let flat = RgbImage::from_pixel(300, 200, image::Rgb([200, 40, 40]));
+        flat.save(output.join("images/flat.png")).unwrap();
+
+        let processor = ImageProcessor::new(&output, &temp_dir.path().join("cache"), config).unwrap();
+        (temp_dir, processor)
+    }
+
+    #[test]
+    fn test_process_image() {
+        let config = ImagesConfig {
+            widths: Some(vec![600, 2000]),
+            ..Default::default()
+        };
+        let (temp_dir, processor) = setup(&config);
+        let output = temp_dir.path().join("public");
+
+        let mut jpeg = fs::read(output.join("images/photo.jpg")).unwrap();
+        let exif = [&[0xFF, 0xE1, 0x00, 0x10][..], b"Exif\0\0MM\0*\0\0\0\x08"].concat();
+        jpeg.splice(2..2, exif);
+        fs::write(output.join("images/photo.jpg"), &jpeg).unwrap();
+
+        let photo = processor.process("/images/photo.jpg").unwrap().expect("photo should be processed");
+        assert_eq!(photo.src, "/images/photo-1200w.jpg");
+        assert_eq!((photo.width, photo.height), (Some(1200), Some(600)));
+        assert_eq!(photo.srcset, "/images/photo-600w.jpg 600w, /images/photo-1200w.jpg 1200w");
+        assert_eq!(photo.sources[0].srcset, "/images/photo-600w.webp 600w, /images/photo-1200w.webp 1200w");
+        let resized = image::open(output.join("images/photo-600w.jpg")).unwrap();
+        assert_eq!((resized.width(), resized.height()), (600, 300));
+        let original = fs::read(output.join("images/photo.jpg")).unwrap();
+        assert_eq!(original, fs::read(output.join("images/photo-1200w.jpg")).unwrap());
+        assert!(!original.windows(4).any(|w| w == b"Exif"));
+
+        let flat = processor.process("images/flat.png").unwrap().expect("png should be processed");
+        assert_eq!(flat.srcset, "/images/flat-300w.png 300w");
+        assert_eq!(
+            flat.sources,
+            vec![ImageSource {
+                mime_type: "image/webp".to_string(),
+                srcset: "/images/flat-300w.webp 300w".to_string(),
+            }]
+        );
+        assert!(output.join("images/flat-300w.webp").exists());
+
+        assert!(processor.process("/images/missing.jpg").unwrap().is_none());
+        assert!(processor.process("/images/logo.svg").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_process_image_uses_cache() {
+        let (temp_dir, processor) = setup(&ImagesConfig::default());
+        let output = temp_dir.path().join("public");
+        let first = processor.process("/images/flat.png").unwrap();
+
+        fs::remove_file(output.join("images/flat-300w.png")).unwrap();
+        let cached_files: Vec<_> = fs::read_dir(temp_dir.path().join("cache")).unwrap().collect();
+        assert_eq!(cached_files.len(), 3);
+
+        let processor = ImageProcessor::new(&output, &temp_dir.path().join("cache"), &ImagesConfig::default()).unwrap();
+        assert_eq!(processor.process("/images/flat.png").unwrap(), first);
+        assert!(output.join("images/flat-300w.png").exists());
+    }
+
+    #[test]
+    fn test_process_image_concurrently() {
+        use rayon::prelude::*;
+
+        let (temp_dir, processor) = setup(&ImagesConfig::default());
+        let output = temp_dir.path().join("public");
+        let results: Vec<_> = (0..8).into_par_iter().map(|_| processor.process("/images/photo.jpg").unwrap()).collect();
+        assert!(results.iter().all(|r| r.is_some() && *r == results[0]));
+        assert_eq!(fs::read(output.join("images/photo.jpg")).unwrap(), fs::read(output.join("images/photo-1200w.jpg")).unwrap());
+        let cached_files = fs::read_dir(temp_dir.path().join("cache")).unwrap().count();
+        assert_eq!(cached_files, 7);
+    }
+
+    #[test]
+    fn test_rewrite_html() {
+        let (_temp_dir, processor) = setup(&ImagesConfig::default());
+
+        let html = r#"<p><img src="../../images/flat.png" alt="Flat"><img src="https://cdn.example.com/x.jpg" alt="Remote"></p>"#;
+        let output = processor.rewrite_html(html, "posts/hello", "https://example.com").unwrap();
+
+        assert_eq!(
+            output,
+            r#"<p><picture><source type="image/webp" srcset="/images/flat-300w.webp 300w"><img src="/images/flat-300w.png" alt="Flat" srcset="/images/flat-300w.png 300w" width="300" height="200" loading="lazy"></picture><img src="https://cdn.example.com/x.jpg" alt="Remote"></p>"#
+        );
+    }
+
+    #[test]
+    fn test_unsupported_format() {
+        let config = ImagesConfig {
+            formats: Some(vec!["avif".to_string()]),
+            ..Default::default()
+        };
+        let err = ImageProcessor::new(Path::new("public"), Path::new("cache"), &config).err().unwrap();
+        assert!(err.to_string().contains("`avif` is not supported"), "{}", err);
+    }
+}
@@ -1,10 +1,29 @@
+pub mod assets;
+pub mod authors;
+pub mod check;
+pub mod compress;
 pub mod config;
 pub mod content;
+pub mod data;
+pub mod diagnostics;
 pub mod domain;
 pub mod engine;
+pub mod error;
+pub mod fingerprint;
+pub mod functions;
+pub mod graph;
+pub mod html;
+pub mod i18n;
+pub mod images;
+pub mod links;
+pub mod minify;
+pub mod navigation;
 pub mod pagination;
 pub mod parser;
+pub mod related;
 pub mod renderer;
 pub mod seo;
+pub mod series;
 pub mod server;
 pub mod taxonomy;
+pub mod theme;
@@ -0,0 +1,218 @@
+use crate::diagnostics::{Diagnostic, Diagnostics};
+use crate::domain::Post;
+use crate::error::{IoResultExt, Result};
+use crate::html::{decode_entities, resolve_local_url, tokenize, Token};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkProblem {
+    MissingTarget,
+    MissingAnchor,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BrokenLink {
+    pub page: String,
+    pub target: String,
+    pub problem: LinkProblem,
+}
+
+impl BrokenLink {
+    pub fn message(&self) -> String {
+        match self.problem {
+            LinkProblem::MissingTarget => format!("Link to `{}` on /{} does not match any page or file", self.target, self.page),
+            LinkProblem::MissingAnchor => format!("Link to `{}` on /{} points to an anchor that does not exist", self.target, self.page),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct LinkReport {
+    pub broken: Vec<BrokenLink>,
+    pub external: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl LinkReport {
+    pub fn diagnostics(&self, posts: &[Post], fatal: bool) -> Diagnostics {
+        let sources: HashMap<String, PathBuf> = posts
+            .iter()
+            .map(|post| (crate::engine::post_path(post), crate::engine::post_source(post)))
+            .collect();
+        let mut diagnostics = Diagnostics::default();
+        for link in &self.broken {
+            let file = sources.get(&link.page).cloned().unwrap_or_else(|| PathBuf::from(&link.page));
+            diagnostics.push(match fatal {
+                true => Diagnostic::error(&file, link.message()),
+                false => Diagnostic::warning(&file, link.message()),
+            });
+        }
+        diagnostics
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+}
+
+pub fn check_output(output_dir: &Path, base_url: &str) -> Result<LinkReport> {
+    let mut files = BTreeSet::new();
+    let mut pages = BTreeMap::new();
+    for entry in WalkDir::new(output_dir).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry
+            .path()
+            .strip_prefix(output_dir)
+            .unwrap_or(entry.path())
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if relative.ends_with(".html") {
+            pages.insert(relative.clone(), fs::read_to_string(entry.path()).io("read", entry.path())?);
+        }
+        files.insert(relative);
+    }
+    Ok(check_links(&pages, &files, base_url))
+}
+
+pub fn check_links(pages: &BTreeMap<String, String>, files: &BTreeSet<String>, base_url: &str) -> LinkReport {
+    let mut anchors: HashMap<&str, HashSet<String>> = HashMap::new();
+    let mut links: Vec<(&str, String)> = Vec::new();
+    for (page, html) in pages.iter().filter(|(path, _)| path.ends_with(".html")) {
+        let ids = anchors.entry(page.as_str()).or_default();
+        for token in tokenize(html) {
+            let Token::StartTag(tag, _) = token else {
+                continue;
+            };
+            ids.extend(tag.attr("id").map(decode_entities));
+            if tag.name.eq_ignore_ascii_case("a") {
+                ids.extend(tag.attr("name").map(decode_entities));
+            }
+            for target in ["href", "src"].iter().filter_map(|name| tag.attr(name)) {
+                let target = decode_entities(target).trim().to_string();
+                if !target.is_empty() {
+                    links.push((page.as_str(), target));
+                }
+            }
+        }
+    }
+
+    let mut report = LinkReport::default();
+    for (page, target) in links {
+        let (file, fragment) = match target.strip_prefix('#') {
+            Some(fragment) => (Some(page.to_string()), format!("#{}", fragment)),
+            None => match resolve_local_url(&target, page.rsplit_once('/').map_or("", |(dir, _)| dir), base_url) {
+                Some(local) => (resolve_file(&local.path, files), local.fragment),
+                None => {
+                    if target.starts_with("http://") || target.starts_with("https://") || target.starts_with("//") {
+                        report.external.entry(target).or_default().insert(page.to_string());
+                    }
+                    continue;
+                }
+            },
+        };
+
+        let problem = match file {
+            None => Some(LinkProblem::MissingTarget),
+            Some(file) => {
+                let id = percent_decode(fragment.trim_start_matches('#'));
+                match anchors.get(file.as_str()) {
+                    Some(ids) if !id.is_empty() && id != "top" && !ids.contains(&id) => Some(LinkProblem::MissingAnchor),
+                    _ => None,
+                }
+            }
+        };
+        if let Some(problem) = problem {
+            report.broken.push(BrokenLink {
+                page: page.to_string(),
+                target,
+                problem,
+            });
+        }
+    }
+    report
+}
+
+pub fn resolve_file(path: &str, files: &BTreeSet<String>) -> Option<String> {
+    let path = percent_decode(path);
+    let candidates = if path.is_empty() || path.ends_with('/') {
+        vec![format!("{}index.html", path)]
+    } else {
+        vec![path.clone(), format!("{}/index.html", path)]
+    };
+    candidates.into_iter().find(|candidate| files.contains(candidate))
+}
+
+fn percent_decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok());
+        match (bytes[i], hex.and_then(|h| u8::from_str_radix(h, 16).ok())) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_links() {
+        let mut pages = BTreeMap::new();
+        pages.insert(
+            "posts/a/index.html".to_string(),
+            concat!(
+                r#"<h2 id="setup">Setup</h2><a href="&#x2F;posts&#x2F;b&#x2F;">B</a> <a href="../missing/">Missing</a> <a href="https://example.com/posts/b">Abs</a>"#,
+                r##"<img src="photo%20one.jpg"> <script src="/js/app.js"></script> <a href="#top">Top</a> <a href="#setup">Setup</a> <a href="#nope">Nope</a>"##,
+                r#"<a href="mailto:me@example.com">Mail</a> <a href="https://other.org/x">External</a> <a href="/">Home</a>"#,
+                r##"<a href="/posts/b/#intro">Intro</a> <a href="/posts/b/#outro">Outro</a> <a href="photo%20one.jpg#x">Photo</a>"##,
+            )
+            .to_string(),
+        );
+        pages.insert("posts/b/index.html".to_string(), r#"<a name="intro"></a><p>B</p><a href="https://other.org/x">Again</a>"#.to_string());
+        pages.insert("index.html".to_string(), r#"<a href="/tags/rust/">Rust</a>"#.to_string());
+        pages.insert("sitemap.xml".to_string(), "<loc>/nowhere/</loc>".to_string());
+
+        let mut files: BTreeSet<String> = pages.keys().cloned().collect();
+        files.insert("posts/a/photo one.jpg".to_string());
+
+        let report = check_links(&pages, &files, "https://example.com");
+        let broken: Vec<(&str, &str, LinkProblem)> = report
+            .broken
+            .iter()
+            .map(|link| (link.page.as_str(), link.target.as_str(), link.problem))
+            .collect();
+        assert_eq!(
+            broken,
+            vec![
+                ("index.html", "/tags/rust/", LinkProblem::MissingTarget),
+                ("posts/a/index.html", "../missing/", LinkProblem::MissingTarget),
+                ("posts/a/index.html", "/js/app.js", LinkProblem::MissingTarget),
+                ("posts/a/index.html", "#nope", LinkProblem::MissingAnchor),
+                ("posts/a/index.html", "/posts/b/#outro", LinkProblem::MissingAnchor),
+            ]
+        );
+
+        let pages: Vec<&String> = report.external["https://other.org/x"].iter().collect();
+        assert_eq!(pages, vec!["posts/a/index.html", "posts/b/index.html"]);
+        assert_eq!(report.external.len(), 1);
+    }
+}
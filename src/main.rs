@@ -1,7 +1,8 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
-use blog_engine::engine::build_site;
+use blog_engine::check::{check_site, CheckOptions};
+use blog_engine::engine::{build_site_with_options, BuildOptions};
 use blog_engine::server::serve;
 
 #[derive(Parser)]
@@ -22,6 +23,22 @@ enum Commands {
 
         #[arg(short, long)]
         drafts: bool,
+
+        #[arg(long, default_value_t = 0)]
+        keep_builds: usize,
+
+        #[arg(long)]
+        keep_going: bool,
+    },
+    Check {
+        #[arg(short, long, default_value = ".")]
+        input: PathBuf,
+
+        #[arg(short, long)]
+        drafts: bool,
+
+        #[arg(long, value_enum, default_value_t = Format::Text)]
+        format: Format,
     },
     Serve {
         #[arg(short, long, default_value = ".")]
@@ -41,14 +58,48 @@ enum Commands {
     },
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Json,
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     tracing_subscriber::fmt::init();
     let cli = Cli::parse();
 
     match &cli.command {
-        Commands::Build { input, output, drafts } => {
-            build_site(input, output, *drafts)?;
+        Commands::Build {
+            input,
+            output,
+            drafts,
+            keep_builds,
+            keep_going,
+        } => {
+            let options = BuildOptions {
+                include_drafts: *drafts,
+                keep_builds: *keep_builds,
+                keep_going: *keep_going,
+                ..Default::default()
+            };
+            build_site_with_options(input, output, &options)?;
+        }
+        Commands::Check { input, drafts, format } => {
+            let options = CheckOptions { include_drafts: *drafts };
+            let diagnostics = check_site(input, &options)?;
+            match format {
+                Format::Text => {
+                    for diagnostic in diagnostics.iter() {
+                        println!("{}", diagnostic);
+                    }
+                    println!("{}", diagnostics.summary());
+                }
+                Format::Json => println!("{}", diagnostics.to_json()?),
+            }
+            if diagnostics.has_errors() {
+                std::process::exit(1);
+            }
         }
         Commands::Serve {
             input,
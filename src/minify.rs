@@ -0,0 +1,522 @@
+use crate::domain::MinifyConfig;
+use crate::html::{tokenize, Token};
+use crate::error::{IoResultExt, Result};
+use rayon::prelude::*;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+pub fn css(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+    let mut pending_space = false;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' => {
+                flush_css_space(&mut out, &mut pending_space);
+                let mut end = i + 1;
+                while end < chars.len() && chars[end] != c {
+                    end += if chars[end] == '\\' { 2 } else { 1 };
+                }
+                let end = (end + 1).min(chars.len());
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    i += 1;
+                }
+                i += 2;
+                pending_space = true;
+                continue;
+            }
+            c if c.is_whitespace() => pending_space = true,
+            '{' | '}' | ';' | ',' | '>' | ':' => {
+                if c == '}' && out.ends_with(';') {
+                    out.pop();
+                }
+                if c == ':' && pending_space && !ends_with_punctuation(&out) && in_selector(&chars[i..]) {
+                    out.push(' ');
+                }
+                pending_space = false;
+                out.push(c);
+            }
+            _ => {
+                flush_css_space(&mut out, &mut pending_space);
+                out.push(c);
+            }
+        }
+        i += 1;
+    }
+    out
+}
+
+fn ends_with_punctuation(out: &str) -> bool {
+    out.is_empty() || out.ends_with(|p: char| "{};,>:".contains(p))
+}
+
+fn in_selector(rest: &[char]) -> bool {
+    rest.iter().find(|c| matches!(c, '{' | ';' | '}')) == Some(&'{')
+}
+
+fn flush_css_space(out: &mut String, pending_space: &mut bool) {
+    if *pending_space && !ends_with_punctuation(out) {
+        out.push(' ');
+    }
+    *pending_space = false;
+}
+
+pub fn js(source: &str) -> String {
+    let chars: Vec<char> = source.chars().collect();
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+    let mut pending_space = false;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '"' | '\'' | '`' => {
+                flush_js_space(&mut out, &mut pending_space, c);
+                let end = skip_js_string(&chars, i);
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let mut had_newline = false;
+                i += 2;
+                while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                    had_newline |= chars[i] == '\n';
+                    i += 1;
+                }
+                i += 2;
+                if had_newline {
+                    push_newline(&mut out);
+                    pending_space = false;
+                } else {
+                    pending_space = true;
+                }
+                continue;
+            }
+            '/' if regex_allowed(&out) => {
+                flush_js_space(&mut out, &mut pending_space, c);
+                let end = skip_js_regex(&chars, i);
+                out.extend(&chars[i..end]);
+                i = end;
+                continue;
+            }
+            '\n' | '\r' => {
+                push_newline(&mut out);
+                pending_space = false;
+            }
+            c if c.is_whitespace() => {
+                if !out.is_empty() && !out.ends_with('\n') {
+                    pending_space = true;
+                }
+            }
+            _ => {
+                flush_js_space(&mut out, &mut pending_space, c);
+                out.push(c);
+            }
+        }
+        i += 1;
+    }
+
+    out.trim_end().to_string()
+}
+
+fn push_newline(out: &mut String) {
+    while out.ends_with(' ') {
+        out.pop();
+    }
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+fn flush_js_space(out: &mut String, pending_space: &mut bool, next: char) {
+    if *pending_space {
+        let previous = out.chars().last().unwrap_or(' ');
+        let needs_space = (is_word_char(previous) && is_word_char(next))
+            || (previous == next && "+-".contains(next))
+            || (previous == '/' && next == '/');
+        if needs_space {
+            out.push(' ');
+        }
+    }
+    *pending_space = false;
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$' || c == '\\' || !c.is_ascii()
+}
+
+fn regex_allowed(out: &str) -> bool {
+    let trimmed = out.trim_end();
+    let Some(last) = trimmed.chars().last() else {
+        return true;
+    };
+    if "(,=:[!&|?{};+-*%<>~^".contains(last) {
+        return true;
+    }
+    let word: String = trimmed
+        .chars()
+        .rev()
+        .take_while(|c| is_word_char(*c))
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+        .collect();
+    matches!(
+        word.as_str(),
+        "return" | "typeof" | "case" | "do" | "else" | "in" | "of" | "new" | "delete" | "void" | "throw" | "yield" | "await"
+    )
+}
+
+fn skip_js_string(chars: &[char], start: usize) -> usize {
+    let quote = chars[start];
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '$' if quote == '`' && chars.get(i + 1) == Some(&'{') => {
+                i = skip_js_template_expression(chars, i + 2);
+            }
+            c if c == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+fn skip_js_template_expression(chars: &[char], start: usize) -> usize {
+    let mut depth = 1;
+    let mut i = start;
+    while i < chars.len() {
+        match chars[i] {
+            '"' | '\'' | '`' => {
+                i = skip_js_string(chars, i);
+                continue;
+            }
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+fn skip_js_regex(chars: &[char], start: usize) -> usize {
+    let mut i = start + 1;
+    let mut in_class = false;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                    i += 1;
+                }
+                return i;
+            }
+            '\n' => return i,
+            _ => {}
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MinifySummary {
+    pub files: usize,
+    pub original_bytes: usize,
+    pub minified_bytes: usize,
+}
+
+impl MinifySummary {
+    pub fn saved_bytes(&self) -> usize {
+        self.original_bytes - self.minified_bytes
+    }
+}
+
+pub fn minify_output(output_dir: &Path, config: &MinifyConfig) -> Result<MinifySummary> {
+    let files: Vec<_> = WalkDir::new(output_dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let minifier: fn(&str) -> String = match e.path().extension().and_then(|ext| ext.to_str())? {
+                "html" | "htm" if config.html.unwrap_or(false) => html,
+                "xml" if config.xml.unwrap_or(false) => xml,
+                "json" if config.json.unwrap_or(false) => json,
+                _ => return None,
+            };
+            Some((e.into_path(), minifier))
+        })
+        .collect();
+
+    let sizes = files
+        .par_iter()
+        .map(|(path, minifier)| -> Result<(usize, usize)> {
+            let source = fs::read_to_string(path).io("read", path)?;
+            let minified = minifier(&source);
+            if minified.len() >= source.len() {
+                return Ok((source.len(), source.len()));
+            }
+            fs::write(path, &minified).io("write", path)?;
+            Ok((source.len(), minified.len()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(MinifySummary {
+        files: sizes.len(),
+        original_bytes: sizes.iter().map(|(original, _)| original).sum(),
+        minified_bytes: sizes.iter().map(|(_, minified)| minified).sum(),
+    })
+}
+
+pub fn html(source: &str) -> String {
+    let tokens = tokenize(source);
+    let mut out = String::with_capacity(source.len());
+    let mut preserve_depth = 0usize;
+    let mut raw_text: Option<(String, Option<String>)> = None;
+
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::StartTag(tag, raw) => {
+                if matches!(tag.name.as_str(), "pre" | "textarea") && !tag.self_closing {
+                    preserve_depth += 1;
+                }
+                if matches!(tag.name.as_str(), "script" | "style") {
+                    raw_text = Some((tag.name.clone(), tag.attr("type").map(|t| t.trim().to_ascii_lowercase())));
+                }
+                out.push_str(raw);
+            }
+            Token::EndTag(name, raw) => {
+                if matches!(name.as_str(), "pre" | "textarea") {
+                    preserve_depth = preserve_depth.saturating_sub(1);
+                }
+                raw_text = None;
+                out.push_str(raw);
+            }
+            Token::Comment(raw) => {
+                if preserve_depth > 0 || raw.starts_with("<!--[if") || raw.starts_with("<!--<![endif") {
+                    out.push_str(raw);
+                }
+            }
+            Token::Other(raw) => out.push_str(raw),
+            Token::Text(text) => {
+                if let Some((name, kind)) = &raw_text {
+                    out.push_str(&minify_raw_text(name, kind.as_deref(), text));
+                    continue;
+                }
+                if preserve_depth > 0 {
+                    out.push_str(text);
+                    continue;
+                }
+
+                let mut collapsed = collapse_whitespace(text);
+                if i == 0 || is_block_boundary(&tokens[i - 1]) || out.ends_with(' ') {
+                    collapsed = collapsed.trim_start().to_string();
+                }
+                if tokens.get(i + 1).is_none_or(is_block_boundary) {
+                    collapsed = collapsed.trim_end().to_string();
+                }
+                out.push_str(&collapsed);
+            }
+        }
+    }
+    out
+}
+
+fn minify_raw_text(element: &str, kind: Option<&str>, text: &str) -> String {
+    match (element, kind) {
+        ("style", None | Some("text/css")) => css(text),
+        ("script", None | Some("text/javascript" | "application/javascript" | "module")) => {
+            js(text)
+        }
+        ("script", Some(kind)) if kind.ends_with("json") => json(text),
+        _ => text.to_string(),
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_space = false;
+    for c in text.chars() {
+        if c.is_ascii_whitespace() {
+            if !in_space {
+                out.push(' ');
+            }
+            in_space = true;
+        } else {
+            out.push(c);
+            in_space = false;
+        }
+    }
+    out
+}
+
+fn is_block_boundary(token: &Token) -> bool {
+    let name = match token {
+        Token::StartTag(tag, _) => tag.name.as_str(),
+        Token::EndTag(name, _) => name.as_str(),
+        Token::Comment(_) | Token::Other(_) => return true,
+        Token::Text(_) => return false,
+    };
+    matches!(
+        name,
+        "html" | "head" | "body" | "title" | "meta" | "link" | "script" | "style" | "noscript" | "base"
+            | "div" | "p" | "ul" | "ol" | "li" | "dl" | "dt" | "dd" | "nav" | "header" | "footer" | "main"
+            | "section" | "article" | "aside" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "hr" | "br"
+            | "table" | "thead" | "tbody" | "tfoot" | "tr" | "td" | "th" | "caption" | "form" | "fieldset"
+            | "figure" | "figcaption" | "blockquote" | "pre" | "address" | "details" | "summary" | "template"
+    )
+}
+
+pub fn xml(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+    while !rest.is_empty() {
+        let (chunk, end) = if rest.starts_with("<!--") {
+            ("", rest.find("-->").map_or(rest.len(), |i| i + 3))
+        } else if rest.starts_with("<![CDATA[") {
+            let end = rest.find("]]>").map_or(rest.len(), |i| i + 3);
+            (&rest[..end], end)
+        } else if rest.starts_with('<') {
+            let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+            (&rest[..end], end)
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            let text = &rest[..end];
+            (if text.trim().is_empty() { "" } else { text }, end)
+        };
+        out.push_str(chunk);
+        rest = &rest[end..];
+    }
+    out
+}
+
+pub fn json(source: &str) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in source.chars() {
+        if in_string {
+            out.push(c);
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            in_string = true;
+            out.push(c);
+        } else if !c.is_whitespace() {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_css() {
+        let source = r#"
+            /* header */
+            body > .nav a:hover ,  .x {
+                color : red ;
+                margin: 0 auto;
+                width: calc(100% - 2px);
+            }
+            .a::before { content: "  /* kept */  "; }
+            div :first-child { top: 0 }
+        "#;
+        assert_eq!(
+            css(source),
+            r#"body>.nav a:hover,.x{color:red;margin:0 auto;width:calc(100% - 2px)}.a::before{content:"  /* kept */  "}div :first-child{top:0}"#
+        );
+    }
+
+    #[test]
+    fn test_js() {
+        let source = r#"
+            // greet the user
+            function greet(name) {
+                /* build message */
+                const msg = `Hello,   ${name + "  // not a comment"}!`;
+                return msg.replace(/\s+\/\//g, ' ') / 2;
+            }
+            let a = b + +c;
+            let url = "http://example.com"; // trailing
+        "#;
+        assert_eq!(
+            js(source),
+            "function greet(name){\nconst msg=`Hello,   ${name + \"  // not a comment\"}!`;\nreturn msg.replace(/\\s+\\/\\//g,' ')/2;\n}\nlet a=b+ +c;\nlet url=\"http://example.com\";"
+        );
+    }
+
+    #[test]
+    fn test_html() {
+        let source = "<!DOCTYPE html>\n<html>\n  <head>\n    <title> My   Blog </title>\n    <!-- analytics -->\n    <script>\n      // setup\n      const x = 1;\n    </script>\n    <script type=\"application/ld+json\">{ \"a\": \"b c\" }</script>\n  </head>\n  <body>\n    <p>Hello,\n      <strong>world</strong> !</p>\n    <pre><code>fn main() {\n    <span>x</span>  y\n}</code></pre>\n    <textarea>  keep\n  me </textarea>\n  </body>\n</html>\n";
+        assert_eq!(
+            html(source),
+            "<!DOCTYPE html><html><head><title>My Blog</title><script>const x=1;</script><script type=\"application/ld+json\">{\"a\":\"b c\"}</script></head><body><p>Hello, <strong>world</strong> !</p><pre><code>fn main() {\n    <span>x</span>  y\n}</code></pre><textarea>  keep\n  me </textarea></body></html>"
+        );
+    }
+
+    #[test]
+    fn test_xml_and_json() {
+        let feed = "<?xml version=\"1.0\"?>\n<!-- feed -->\n<rss>\n  <channel>\n    <title>A &amp; B</title>\n    <description><![CDATA[ <p>x</p>  ]]></description>\n  </channel>\n</rss>\n";
+        assert_eq!(
+            xml(feed),
+            "<?xml version=\"1.0\"?><rss><channel><title>A &amp; B</title><description><![CDATA[ <p>x</p>  ]]></description></channel></rss>"
+        );
+        assert_eq!(json("[\n  { \"title\": \"a \\\" b\", \"n\": 1 }\n]"), "[{\"title\":\"a \\\" b\",\"n\":1}]");
+    }
+
+    #[test]
+    fn test_minify_output() {
+        let temp_dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        let output = temp_dir.path();
+        fs::write(output.join("index.html"), "<div>\n  <p>x</p>\n</div>\n").unwrap();
+        fs::write(output.join("search.json"), "[ 1, 2 ]").unwrap();
+        fs::write(output.join("style.css"), "a { color: red; }").unwrap();
+
+        let config = MinifyConfig {
+            html: Some(true),
+            ..Default::default()
+        };
+        let summary = minify_output(output, &config).unwrap();
+
+        assert_eq!(fs::read_to_string(output.join("index.html")).unwrap(), "<div><p>x</p></div>");
+        assert_eq!(fs::read_to_string(output.join("search.json")).unwrap(), "[ 1, 2 ]");
+        assert_eq!(fs::read_to_string(output.join("style.css")).unwrap(), "a { color: red; }");
+        assert_eq!(summary.files, 1);
+        assert_eq!(summary.saved_bytes(), 24 - 19);
+    }
+}
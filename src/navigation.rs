@@ -0,0 +1,119 @@
+use crate::domain::{Breadcrumb, Config, Post, PostLink};
+use crate::error::{BlogError, Result};
+use crate::functions::slugify;
+use std::collections::BTreeMap;
+
+pub fn link_neighbours(posts: &mut [Post], config: &Config) -> Result<()> {
+    let scope = config.navigation.scope.as_deref().unwrap_or("site");
+    if !matches!(scope, "site" | "section" | "series") {
+        return Err(BlogError::InvalidConfig(format!(
+            "Unknown navigation scope `{}`, expected `site`, `section` or `series`",
+            scope
+        )));
+    }
+
+    let mut groups: BTreeMap<Option<String>, Vec<usize>> = BTreeMap::new();
+    for (i, post) in posts.iter().enumerate() {
+        let key = match scope {
+            "section" => post.section.clone(),
+            "series" => post.meta.series.as_deref().map(slugify),
+            _ => None,
+        };
+        groups.entry(key).or_default().push(i);
+    }
+    let neighbours: Vec<(usize, Option<usize>, Option<usize>)> = groups
+        .values()
+        .flat_map(|group| {
+            group.iter().enumerate().map(|(k, &i)| {
+                let newer = k.checked_sub(1).map(|k| group[k]);
+                let older = group.get(k + 1).copied();
+                (i, older, newer)
+            })
+        })
+        .collect();
+
+    let summaries: Vec<PostLink> = posts.iter().map(PostLink::of).collect();
+    for (i, older, newer) in neighbours {
+        let post = &mut posts[i];
+        post.previous = older.map(|j| summaries[j].clone());
+        post.next = newer.map(|j| summaries[j].clone());
+        post.breadcrumbs = breadcrumbs(post, config);
+    }
+    Ok(())
+}
+
+fn breadcrumbs(post: &Post, config: &Config) -> Vec<Breadcrumb> {
+    let home = config.language(&post.lang);
+    let mut trail = vec![Breadcrumb {
+        title: home.title,
+        url: Some(home.url),
+    }];
+    if let Some(section) = &post.section {
+        trail.push(Breadcrumb {
+            title: section.clone(),
+            url: None,
+        });
+    }
+    trail.push(Breadcrumb {
+        title: post.meta.title.clone(),
+        url: Some(post.url.clone()),
+    });
+    trail
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::NavigationConfig;
+
+    fn post(slug: &str, section: Option<&str>, series: Option<&str>) -> Post {
+        let mut post = Post::fixture(slug);
+        post.meta.series = series.map(str::to_string);
+        post.section = section.map(str::to_string);
+        post
+    }
+
+    fn slug(link: &Option<PostLink>) -> Option<&str> {
+        link.as_ref().map(|l| l.slug.as_str())
+    }
+
+    #[test]
+    fn test_link_neighbours() {
+        let mut posts = vec![
+            post("newest", Some("notes"), Some("Rust Tutorial")),
+            post("middle", None, None),
+            post("oldest", Some("notes"), Some("rust tutorial")),
+        ];
+        let mut config = Config {
+            title: "Blog".to_string(),
+            ..Default::default()
+        };
+
+        link_neighbours(&mut posts, &config).unwrap();
+        assert_eq!((slug(&posts[0].previous), slug(&posts[0].next)), (Some("middle"), None));
+        assert_eq!((slug(&posts[1].previous), slug(&posts[1].next)), (Some("oldest"), Some("newest")));
+        assert_eq!((slug(&posts[2].previous), slug(&posts[2].next)), (None, Some("middle")));
+
+        let titles: Vec<&str> = posts[0].breadcrumbs.iter().map(|b| b.title.as_str()).collect();
+        assert_eq!(titles, vec!["Blog", "notes", "NEWEST"]);
+        assert_eq!(posts[0].breadcrumbs[1].url, None);
+        assert_eq!(posts[1].breadcrumbs.len(), 2);
+
+        config.navigation = NavigationConfig {
+            scope: Some("section".to_string()),
+        };
+        link_neighbours(&mut posts, &config).unwrap();
+        assert_eq!((slug(&posts[0].previous), slug(&posts[0].next)), (Some("oldest"), None));
+        assert_eq!((slug(&posts[1].previous), slug(&posts[1].next)), (None, None));
+        assert_eq!((slug(&posts[2].previous), slug(&posts[2].next)), (None, Some("newest")));
+
+        config.navigation.scope = Some("series".to_string());
+        link_neighbours(&mut posts, &config).unwrap();
+        assert_eq!((slug(&posts[0].previous), slug(&posts[0].next)), (Some("oldest"), None));
+        assert_eq!((slug(&posts[1].previous), slug(&posts[1].next)), (None, None));
+        assert_eq!((slug(&posts[2].previous), slug(&posts[2].next)), (None, Some("newest")));
+
+        config.navigation.scope = Some("galaxy".to_string());
+        assert!(link_neighbours(&mut posts, &config).is_err());
+    }
+}
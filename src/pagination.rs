@@ -1,35 +1,67 @@
 use serde::Serialize;
 
-#[derive(Debug, Serialize, PartialEq)]
+const WINDOW: usize = 2;
+
+#[derive(Debug, Default, Serialize, PartialEq)]
 pub struct Paginator<T> {
     pub current_page: usize,
     pub total_pages: usize,
     pub items: Vec<T>,
+    pub first_url: String,
+    pub last_url: String,
+    pub previous_url: Option<String>,
+    pub next_url: Option<String>,
+    pub pages: Vec<PageLink>,
 }
 
-pub fn paginate<T: Clone>(items: &[T], per_page: usize) -> Vec<Paginator<T>> {
-    if items.is_empty() {
-        return vec![Paginator {
-            current_page: 1,
-            total_pages: 1,
-            items: vec![],
-        }];
-    }
-    
-    let total_pages = (items.len() as f64 / per_page as f64).ceil() as usize;
-    let mut pages = Vec::new();
-
-    for i in 0..total_pages {
-        let start = i * per_page;
-        let end = std::cmp::min(start + per_page, items.len());
-        pages.push(Paginator {
-            current_page: i + 1,
-            total_pages,
-            items: items[start..end].to_vec(),
-        });
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PageLink {
+    pub number: usize,
+    pub url: String,
+    pub current: bool,
+}
+
+pub fn page_url(page: usize) -> String {
+    page_url_under("/", page)
+}
+
+pub fn page_url_under(home: &str, page: usize) -> String {
+    match page {
+        0 | 1 => home.to_string(),
+        page => format!("{}page/{}/", home, page),
     }
+}
+
+pub fn paginate<T: Clone>(items: &[T], per_page: usize) -> Vec<Paginator<T>> {
+    paginate_under(items, per_page, "/")
+}
 
-    pages
+pub fn paginate_under<T: Clone>(items: &[T], per_page: usize, home: &str) -> Vec<Paginator<T>> {
+    let page_url = |page| page_url_under(home, page);
+    let total_pages = items.len().div_ceil(per_page.max(1)).max(1);
+    (1..=total_pages)
+        .map(|page| {
+            let start = (page - 1) * per_page;
+            let end = std::cmp::min(start + per_page, items.len());
+            let window = page.saturating_sub(WINDOW).max(1)..=(page + WINDOW).min(total_pages);
+            Paginator {
+                current_page: page,
+                total_pages,
+                items: items[start.min(end)..end].to_vec(),
+                first_url: page_url(1),
+                last_url: page_url(total_pages),
+                previous_url: (page > 1).then(|| page_url(page - 1)),
+                next_url: (page < total_pages).then(|| page_url(page + 1)),
+                pages: window
+                    .map(|number| PageLink {
+                        number,
+                        url: page_url(number),
+                        current: number == page,
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -49,4 +81,33 @@ mod tests {
         assert_eq!(pages[2].items, vec![5]);
         assert_eq!(pages[2].current_page, 3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_paginate_urls_and_window() {
+        let items: Vec<usize> = (1..=20).collect();
+        let pages = paginate(&items, 2);
+
+        assert_eq!(pages[0].previous_url, None);
+        assert_eq!(pages[0].next_url.as_deref(), Some("/page/2/"));
+        assert_eq!(pages[0].first_url, "/");
+        assert_eq!(pages[0].last_url, "/page/10/");
+        assert_eq!(pages[1].previous_url.as_deref(), Some("/"));
+        assert_eq!(pages[9].next_url, None);
+
+        let window = |page: &Paginator<usize>| page.pages.iter().map(|p| p.number).collect::<Vec<_>>();
+        assert_eq!(window(&pages[0]), vec![1, 2, 3]);
+        assert_eq!(window(&pages[4]), vec![3, 4, 5, 6, 7]);
+        assert_eq!(window(&pages[9]), vec![8, 9, 10]);
+        assert!(pages[4].pages[2].current);
+        assert_eq!(pages[4].pages[2].url, "/page/5/");
+
+        let empty = paginate::<usize>(&[], 10);
+        assert_eq!(empty.len(), 1);
+        assert_eq!(empty[0].total_pages, 1);
+        assert_eq!(empty[0].last_url, "/");
+
+        let translated = paginate_under(&items, 10, "/es/");
+        assert_eq!(translated[0].first_url, "/es/");
+        assert_eq!(translated[0].next_url.as_deref(), Some("/es/page/2/"));
+    }
+}
@@ -1,30 +1,246 @@
 use crate::domain::{Post, PostMeta};
-use anyhow::{Context, Result};
+use crate::error::{BlogError, Result};
 use gray_matter::engine::YAML;
-use gray_matter::Matter;
-use pulldown_cmark::{html, Options, Parser};
+use gray_matter::{Matter, ParsedEntity};
+use pulldown_cmark::{html, CowStr, Event, LinkType, Options, Parser, Tag};
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::PathBuf;
 
-pub fn parse_markdown(content: &str) -> Result<Post> {
-    let matter = Matter::<YAML>::new();
-    let result = matter.parse(content);
+#[derive(Debug, Default, Clone)]
+pub struct CrossReferences {
+    paths: HashMap<String, Target>,
+    slugs: HashMap<String, Vec<Target>>,
+}
+
+#[derive(Debug, Clone)]
+struct Target {
+    url: String,
+    title: String,
+    lang: String,
+}
+
+impl CrossReferences {
+    pub fn insert(&mut self, path: &str, meta: &PostMeta) {
+        self.insert_translation(path, meta, "", permalink(&meta.slug));
+    }
+
+    pub fn insert_translation(&mut self, path: &str, meta: &PostMeta, lang: &str, url: String) {
+        let target = Target {
+            url,
+            title: meta.title.clone(),
+            lang: lang.to_string(),
+        };
+        let path = path.trim_matches('/');
+        let translated_index = format!("/index.{}.md", lang);
+        if let Some(bundle) = path.strip_suffix("/index.md").or_else(|| path.strip_suffix(translated_index.as_str())) {
+            self.paths.entry(bundle.to_string()).or_insert_with(|| target.clone());
+        }
+        self.paths.entry(path.to_string()).or_insert_with(|| target.clone());
+        let targets = self.slugs.entry(meta.slug.clone()).or_default();
+        if !targets.iter().any(|t| t.lang == lang) {
+            targets.push(target);
+        }
+    }
+
+    fn resolve_path(&self, reference: &str) -> Option<String> {
+        let (path, fragment) = split_fragment(reference.strip_prefix("@/")?);
+        let target = self.paths.get(path.trim_matches('/'))?;
+        Some(format!("{}{}", target.url, fragment))
+    }
+
+    fn resolve_wikilink(&self, reference: &str, lang: &str) -> Option<(String, String)> {
+        let (name, fragment) = split_fragment(reference.trim());
+        let target = match name.ends_with(".md") {
+            true => self.paths.get(name.trim_matches('/')),
+            false => self.slugs.get(name).and_then(|targets| targets.iter().find(|t| t.lang == lang).or(targets.first())),
+        }?;
+        Some((format!("{}{}", target.url, fragment), target.title.clone()))
+    }
+}
+
+fn split_fragment(reference: &str) -> (&str, &str) {
+    match reference.find('#') {
+        Some(index) => reference.split_at(index),
+        None => (reference, ""),
+    }
+}
+
+pub fn permalink(slug: &str) -> String {
+    format!("/posts/{}/", slug)
+}
 
-    let meta: PostMeta = result
-        .data
-        .ok_or_else(|| anyhow::anyhow!("No frontmatter found"))?
-        .deserialize()
-        .context("Failed to deserialize frontmatter")?;
+pub fn parse_frontmatter(content: &str) -> Result<PostMeta> {
+    let (meta, _) = split_frontmatter(content)?;
+    Ok(meta)
+}
+
+pub fn parse_markdown(content: &str, references: &CrossReferences) -> Result<Post> {
+    parse_translation(content, references, "")
+}
+
+pub fn parse_translation(content: &str, references: &CrossReferences, lang: &str) -> Result<Post> {
+    let (meta, result) = split_frontmatter(content)?;
+
+    let body_start = content
+        .find(&result.matter)
+        .map(|start| start + result.matter.len())
+        .and_then(|end| content[end..].find(&result.content).map(|start| start + end))
+        .unwrap_or(0);
+    let events = resolve_references(&result.content, references, lang).map_err(|(offset, target)| {
+        let (line, column) = location(content, body_start + offset);
+        BlogError::BrokenReference {
+            path: PathBuf::new(),
+            line,
+            column,
+            target,
+        }
+    })?;
 
     let mut html_output = String::new();
-    let options = Options::all();
-    let parser = Parser::new_ext(&result.content, options);
-    html::push_html(&mut html_output, parser);
+    html::push_html(&mut html_output, events.into_iter());
 
     Ok(Post {
+        url: permalink(&meta.slug),
         meta,
         content: html_output,
+        ..Default::default()
     })
 }
 
+fn split_frontmatter(content: &str) -> Result<(PostMeta, ParsedEntity)> {
+    let matter = Matter::<YAML>::new();
+    let result = matter.parse(content);
+
+    if result.matter.trim().is_empty() {
+        return Err(BlogError::Frontmatter {
+            path: PathBuf::new(),
+            line: 1,
+            column: None,
+            message: "No frontmatter found".to_string(),
+        });
+    }
+
+    let offset = content.find(&result.matter).map_or(1, |start| content[..start].lines().count());
+    let meta: PostMeta = serde_yaml::from_str(&result.matter).map_err(|e| {
+        let message = strip_locations(&e.to_string());
+        BlogError::Frontmatter {
+            path: PathBuf::new(),
+            line: e.location().map_or(offset, |l| l.line() + offset),
+            column: e.location().map(|l| l.column()),
+            message: format!("Invalid frontmatter: {}", message),
+        }
+    })?;
+
+    Ok((meta, result))
+}
+
+fn resolve_references<'a>(body: &'a str, references: &CrossReferences, lang: &str) -> std::result::Result<Vec<Event<'a>>, (usize, String)> {
+    let mut events = Vec::new();
+    let mut text: Option<(String, usize)> = None;
+    let mut in_code_block = false;
+
+    for (event, range) in Parser::new_ext(body, Options::all()).into_offset_iter() {
+        if let (Event::Text(chunk), false) = (&event, in_code_block) {
+            text.get_or_insert_with(|| (String::new(), range.start)).0.push_str(chunk);
+            continue;
+        }
+        if let Some((chunk, offset)) = text.take() {
+            push_wikilinks(&mut events, &chunk, offset, references, lang)?;
+        }
+
+        let event = match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                in_code_block = true;
+                Event::Start(Tag::CodeBlock(kind))
+            }
+            Event::End(Tag::CodeBlock(kind)) => {
+                in_code_block = false;
+                Event::End(Tag::CodeBlock(kind))
+            }
+            Event::Start(Tag::Link(kind, url, title)) => Event::Start(Tag::Link(kind, resolve_url(url, &range, references)?, title)),
+            Event::End(Tag::Link(kind, url, title)) => Event::End(Tag::Link(kind, resolve_url(url, &range, references)?, title)),
+            event => event,
+        };
+        events.push(event);
+    }
+    if let Some((chunk, offset)) = text.take() {
+        push_wikilinks(&mut events, &chunk, offset, references, lang)?;
+    }
+    Ok(events)
+}
+
+fn resolve_url<'a>(url: CowStr<'a>, range: &Range<usize>, references: &CrossReferences) -> std::result::Result<CowStr<'a>, (usize, String)> {
+    if !url.starts_with("@/") {
+        return Ok(url);
+    }
+    match references.resolve_path(&url) {
+        Some(resolved) => Ok(resolved.into()),
+        None => Err((range.start, url.to_string())),
+    }
+}
+
+fn push_wikilinks<'a>(
+    events: &mut Vec<Event<'a>>,
+    text: &str,
+    offset: usize,
+    references: &CrossReferences,
+    lang: &str,
+) -> std::result::Result<(), (usize, String)> {
+    let mut rest = text;
+    let mut consumed = 0;
+    while let Some(start) = rest.find("[[") {
+        let Some(length) = rest[start + 2..].find("]]") else {
+            break;
+        };
+        let inner = &rest[start + 2..start + 2 + length];
+        let (reference, label) = match inner.split_once('|') {
+            Some((reference, label)) => (reference, Some(label.trim())),
+            None => (inner, None),
+        };
+        let (url, title) = references
+            .resolve_wikilink(reference, lang)
+            .ok_or_else(|| (offset + consumed + start, format!("[[{}]]", inner)))?;
+
+        if start > 0 {
+            events.push(Event::Text(rest[..start].to_string().into()));
+        }
+        let link = Tag::Link(LinkType::Inline, url.into(), "".into());
+        events.push(Event::Start(link.clone()));
+        events.push(Event::Text(label.map_or(title, str::to_string).into()));
+        events.push(Event::End(link));
+
+        let end = start + 2 + length + 2;
+        consumed += end;
+        rest = &rest[end..];
+    }
+    if !rest.is_empty() {
+        events.push(Event::Text(rest.to_string().into()));
+    }
+    Ok(())
+}
+
+pub(crate) fn location(content: &str, offset: usize) -> (usize, Option<usize>) {
+    let before = &content[..offset.min(content.len())];
+    let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+    (before.matches('\n').count() + 1, Some(before[line_start..].chars().count() + 1))
+}
+
+pub(crate) fn strip_locations(message: &str) -> String {
+    let mut output = String::with_capacity(message.len());
+    let mut rest = message;
+    while let Some(start) = rest.find(" at line ") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + " at line ".len()..];
+        let skip = after
+            .find(|c: char| !(c.is_ascii_digit() || c == ' ' || "column".contains(c)))
+            .unwrap_or(after.len());
+        rest = &after[skip..];
+    }
+    output.push_str(rest);
+    output
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -45,16 +261,83 @@ This is a test post.
             title: "Hello World".to_string(),
             date: "2023-10-27".to_string(),
             slug: "hello-world".to_string(),
-            tags: None,
-            categories: None,
-            draft: None,
-            image: None,
+            ..Default::default()
         };
 
-        let result = parse_markdown(raw_content).expect("Failed to parse markdown");
+        let result = parse_markdown(raw_content, &CrossReferences::default()).expect("Failed to parse markdown");
 
         assert_eq!(result.meta, expected_meta);
         assert!(result.content.contains("<h1>Welcome</h1>"));
         assert!(result.content.contains("<p>This is a test post.</p>"));
+        assert_eq!(result.url, "/posts/hello-world/");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse_layout_alias() {
+        let raw_content = "---\ntitle: Landing\ndate: 2023-10-27\nslug: landing\nlayout: landing.html\n---\nHi\n";
+
+        let result = parse_markdown(raw_content, &CrossReferences::default()).expect("Failed to parse markdown");
+
+        assert_eq!(result.meta.template, Some("landing.html".to_string()));
+    }
+
+    #[test]
+    fn test_frontmatter_error_location() {
+        let raw_content = "---\ntitle: Broken\ndate: 2023-10-27\ntags: [rust\nslug: broken\n---\nBody\n";
+        let Err(BlogError::Frontmatter { line, column, message, .. }) = parse_markdown(raw_content, &CrossReferences::default()) else {
+            panic!("Expected a frontmatter error");
+        };
+        assert_eq!((line, column), (5, Some(5)));
+        assert_eq!(message, "Invalid frontmatter: did not find expected ',' or ']', while parsing a flow sequence");
+
+        let raw_content = "---\ntitle: Missing slug\ndate: 2023-10-27\n---\nBody\n";
+        let Err(BlogError::Frontmatter { line, message, .. }) = parse_markdown(raw_content, &CrossReferences::default()) else {
+            panic!("Expected a frontmatter error");
+        };
+        assert_eq!(line, 2);
+        assert_eq!(message, "Invalid frontmatter: missing field `slug`");
+
+        let err = parse_markdown("# No frontmatter\n", &CrossReferences::default()).unwrap_err();
+        assert_eq!(err.to_string(), "No frontmatter found");
+    }
+
+    #[test]
+    fn test_cross_references() {
+        let mut references = CrossReferences::default();
+        let meta = |slug: &str, title: &str| PostMeta {
+            title: title.to_string(),
+            slug: slug.to_string(),
+            ..Default::default()
+        };
+        references.insert("posts/async-traits.md", &meta("async-traits-in-depth", "Async Traits"));
+        references.insert("guides/setup/index.md", &meta("setup", "Setup"));
+
+        let raw_content = concat!(
+            "---\ntitle: Links\ndate: 2023-10-27\nslug: links\n---\n",
+            "See [traits](@/posts/async-traits.md#section), [setup](@/guides/setup/) and [[async-traits-in-depth]].\n\n",
+            "Also [[setup|the setup guide]] or [[setup#install]], but not `[[code]]`:\n\n",
+            "```\n[[block]] [x](@/nope.md)\n```\n",
+        );
+        let post = parse_markdown(raw_content, &references).expect("Failed to parse markdown");
+        assert!(post.content.contains(r#"<a href="/posts/async-traits-in-depth/#section">traits</a>"#), "{}", post.content);
+        assert!(post.content.contains(r#"<a href="/posts/setup/">setup</a>"#), "{}", post.content);
+        assert!(post.content.contains(r#"<a href="/posts/async-traits-in-depth/">Async Traits</a>."#), "{}", post.content);
+        assert!(post.content.contains(r#"Also <a href="/posts/setup/">the setup guide</a> or <a href="/posts/setup/#install">Setup</a>"#), "{}", post.content);
+        assert!(post.content.contains("<code>[[code]]</code>"), "{}", post.content);
+        assert!(post.content.contains("[[block]] [x](@/nope.md)"), "{}", post.content);
+
+        let raw_content = "---\ntitle: Broken\ndate: 2023-10-27\nslug: broken\n---\nIntro\n\nRead [this](@/posts/missing.md) first.\n";
+        let Err(BlogError::BrokenReference { line, column, target, .. }) = parse_markdown(raw_content, &references) else {
+            panic!("Expected a broken reference error");
+        };
+        assert_eq!((line, column, target.as_str()), (8, Some(6), "@/posts/missing.md"));
+
+        let raw_content = "---\ntitle: Broken\ndate: 2023-10-27\nslug: broken\n---\nSee *also* [[gone]].\n";
+        let err = parse_markdown(raw_content, &references).unwrap_err();
+        let BlogError::BrokenReference { line, column, .. } = &err else {
+            panic!("Expected a broken reference error");
+        };
+        assert_eq!((*line, *column), (6, Some(12)));
+        assert_eq!(err.to_string(), "Cross-reference `[[gone]]` does not match any post");
+    }
+}
@@ -0,0 +1,178 @@
+use crate::domain::{Post, PostLink, RelatedConfig};
+use crate::functions::strip_tags;
+use rayon::prelude::*;
+use std::collections::{BTreeMap, BTreeSet};
+
+const DEFAULT_COUNT: usize = 5;
+const DEFAULT_TEXT_WEIGHT: f64 = 0.5;
+
+pub fn relate_posts(posts: &mut [Post], config: &RelatedConfig) {
+    let count = config.count.unwrap_or(DEFAULT_COUNT);
+    if count == 0 || posts.len() < 2 {
+        return;
+    }
+
+    let terms: Vec<BTreeSet<String>> = posts.iter().map(taxonomy_terms).collect();
+    let term_weights: BTreeMap<&str, f64> = document_frequencies(terms.iter().map(|t| t.iter().map(String::as_str)))
+        .into_iter()
+        .map(|(term, df)| (term, (1.0 + posts.len() as f64 / df as f64).ln()))
+        .collect();
+
+    let text_weight = match config.text.unwrap_or(false) {
+        true => config.text_weight.unwrap_or(DEFAULT_TEXT_WEIGHT).clamp(0.0, 1.0),
+        false => 0.0,
+    };
+    let vectors = match text_weight > 0.0 {
+        true => tf_idf_vectors(posts),
+        false => Vec::new(),
+    };
+
+    let related: Vec<Vec<usize>> = (0..posts.len())
+        .into_par_iter()
+        .map(|i| {
+            let own: f64 = terms[i].iter().map(|t| term_weights[t.as_str()]).sum();
+            let mut scored: Vec<(f64, usize)> = (0..posts.len())
+                .filter(|&j| j != i)
+                .filter_map(|j| {
+                    let shared: f64 = terms[i].intersection(&terms[j]).map(|t| term_weights[t.as_str()]).sum();
+                    let taxonomy = if own > 0.0 { shared / own } else { 0.0 };
+                    let text = if vectors.is_empty() { 0.0 } else { cosine(&vectors[i], &vectors[j]) };
+                    let score = (1.0 - text_weight) * taxonomy + text_weight * text;
+                    (score > 0.0).then_some((score, j))
+                })
+                .collect();
+            scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)));
+            scored.into_iter().take(count).map(|(_, j)| j).collect()
+        })
+        .collect();
+
+    let summaries: Vec<PostLink> = posts.iter().map(PostLink::of).collect();
+    for (post, related) in posts.iter_mut().zip(related) {
+        post.related = related.into_iter().map(|j| summaries[j].clone()).collect();
+    }
+}
+
+fn taxonomy_terms(post: &Post) -> BTreeSet<String> {
+    let tags = post.meta.tags.iter().flatten().map(|t| format!("tag:{}", t.to_lowercase()));
+    let categories = post.meta.categories.iter().flatten().map(|c| format!("category:{}", c.to_lowercase()));
+    tags.chain(categories).collect()
+}
+
+fn document_frequencies<'a, D>(documents: impl Iterator<Item = D>) -> BTreeMap<&'a str, usize>
+where
+    D: Iterator<Item = &'a str>,
+{
+    let mut frequencies = BTreeMap::new();
+    for document in documents {
+        for term in document {
+            *frequencies.entry(term).or_insert(0) += 1;
+        }
+    }
+    frequencies
+}
+
+fn tf_idf_vectors(posts: &[Post]) -> Vec<BTreeMap<String, f64>> {
+    let counts: Vec<BTreeMap<String, usize>> = posts
+        .par_iter()
+        .map(|post| {
+            let mut counts = BTreeMap::new();
+            for word in strip_tags(&post.content)
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|w| w.chars().count() >= 3)
+            {
+                *counts.entry(word.to_lowercase()).or_insert(0) += 1;
+            }
+            counts
+        })
+        .collect();
+
+    let frequencies = document_frequencies(counts.iter().map(|c| c.keys().map(String::as_str)));
+    let total = posts.len() as f64;
+    counts
+        .iter()
+        .map(|counts| {
+            let words: usize = counts.values().sum();
+            counts
+                .iter()
+                .map(|(word, count)| {
+                    let idf = (total / frequencies[word.as_str()] as f64).ln();
+                    (word.clone(), *count as f64 / words as f64 * idf)
+                })
+                .filter(|(_, weight)| *weight > 0.0)
+                .collect()
+        })
+        .collect()
+}
+
+fn cosine(a: &BTreeMap<String, f64>, b: &BTreeMap<String, f64>) -> f64 {
+    let dot: f64 = a.iter().filter_map(|(word, x)| b.get(word).map(|y| x * y)).sum();
+    let norm = |v: &BTreeMap<String, f64>| v.values().map(|x| x * x).sum::<f64>().sqrt();
+    match norm(a) * norm(b) {
+        n if n > 0.0 => dot / n,
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(slug: &str, tags: &[&str], content: &str) -> Post {
+        let mut post = Post::fixture(slug);
+        post.meta.tags = Some(tags.iter().map(|t| t.to_string()).collect());
+        post.content = content.to_string();
+        post
+    }
+
+    fn related(posts: &[Post], slug: &str) -> Vec<String> {
+        let post = posts.iter().find(|p| p.meta.slug == slug).unwrap();
+        post.related.iter().map(|r| r.slug.clone()).collect()
+    }
+
+    #[test]
+    fn test_related_by_rare_tags() {
+        let mut posts = vec![
+            post("a", &["rust", "async"], ""),
+            post("b", &["rust"], ""),
+            post("c", &["rust", "async"], ""),
+            post("d", &["rust", "wasm"], ""),
+            post("e", &["cooking"], ""),
+        ];
+        relate_posts(&mut posts, &RelatedConfig::default());
+
+        assert_eq!(related(&posts, "a"), vec!["c", "b", "d"]);
+        assert_eq!(related(&posts, "e"), Vec::<String>::new());
+        assert_eq!(posts[0].related[0].title, "C");
+
+        relate_posts(
+            &mut posts,
+            &RelatedConfig {
+                count: Some(1),
+                ..Default::default()
+            },
+        );
+        assert_eq!(related(&posts, "a"), vec!["c"]);
+    }
+
+    #[test]
+    fn test_related_by_text() {
+        let mut posts = vec![
+            post("a", &["notes"], "<p>Borrow checker lifetimes explained with borrow examples</p>"),
+            post("b", &["notes"], "<p>Sourdough bread baking with a starter</p>"),
+            post("c", &["notes"], "<p>Understanding lifetimes and the borrow checker</p>"),
+        ];
+        let config = RelatedConfig {
+            text: Some(true),
+            text_weight: Some(0.8),
+            ..Default::default()
+        };
+        relate_posts(&mut posts, &config);
+        assert_eq!(related(&posts, "a"), vec!["c", "b"]);
+        assert_eq!(related(&posts, "b"), vec!["a", "c"]);
+
+        let first: Vec<Vec<String>> = posts.iter().map(|p| related(&posts, &p.meta.slug)).collect();
+        relate_posts(&mut posts, &config);
+        let second: Vec<Vec<String>> = posts.iter().map(|p| related(&posts, &p.meta.slug)).collect();
+        assert_eq!(first, second);
+    }
+}
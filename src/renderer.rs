@@ -1,56 +1,270 @@
-use crate::domain::{Config, Post};
-use anyhow::Result;
-use std::path::Path;
+use crate::domain::{Author, Config, Language, Post};
+use crate::theme::{TemplateSource, Theme};
+use crate::error::{BlogError, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use tera::{Context, Tera};
 
 pub struct Renderer {
     tera: Tera,
+    sources: BTreeMap<String, TemplateSource>,
+    theme_config: toml::Table,
+    assets: BTreeMap<String, String>,
+    data: tera::Value,
 }
 
 impl Renderer {
     pub fn new<P: AsRef<Path>>(theme_dir: P) -> Result<Self> {
-        let theme_dir = theme_dir.as_ref();
-        let glob = theme_dir.join("**/*.html");
-        let glob_str = glob.to_str().ok_or_else(|| anyhow::anyhow!("Invalid theme directory path"))?;
-        let mut tera = Tera::new(glob_str)?;
-        tera.autoescape_on(vec![]); 
-        Ok(Self { tera })
+        Self::from_theme(&Theme::from_dir(theme_dir), &Config::default())
     }
 
-    pub fn render_post(&self, post: &Post, config: &Config) -> Result<String> {
+    pub fn from_theme(theme: &Theme, config: &Config) -> Result<Self> {
+        let sources = theme.resolve_templates()?;
+        for (name, source) in &sources {
+            tracing::debug!("Template {} resolved to {}", name, source);
+        }
+        for (theme_name, manifest) in &theme.manifests {
+            tracing::debug!(
+                "Using theme `{}` ({} {})",
+                theme_name,
+                manifest.name.as_deref().unwrap_or(theme_name),
+                manifest.version.as_deref().unwrap_or("unversioned")
+            );
+        }
+        let theme_config = theme.validate(&sources, &config.theme_config)?;
+
+        let mut tera = Tera::default();
+        if config.autoescape.unwrap_or(true) {
+            tera.autoescape_on(vec![".html", ".xml"]);
+        } else {
+            tracing::warn!("Autoescaping is disabled by `autoescape = false`; template variables are inserted unescaped");
+            tera.autoescape_on(vec![]);
+        }
+        crate::functions::register_filters(&mut tera);
+        tera.add_template_files(
+            sources
+                .iter()
+                .map(|(name, source)| (source.path.clone(), Some(name.clone())))
+                .collect::<Vec<(_, Option<String>)>>(),
+        )
+        .map_err(|source| BlogError::TemplateLoad {
+            theme: theme.name.clone(),
+            resolution: describe_sources(&sources),
+            source,
+        })?;
+
+        Ok(Self {
+            tera,
+            sources,
+            theme_config,
+            assets: BTreeMap::new(),
+            data: tera::Value::Object(Default::default()),
+        })
+    }
+
+    pub fn register_site(
+        &mut self,
+        posts: &[Post],
+        config: &Config,
+        static_dirs: Vec<PathBuf>,
+        fingerprinted: BTreeMap<String, String>,
+    ) {
+        crate::functions::register_site_functions(&mut self.tera, posts, config, static_dirs, fingerprinted);
+    }
+
+    pub fn register_images(&mut self, images: std::sync::Arc<crate::images::ImageProcessor>) {
+        crate::functions::register_image_function(&mut self.tera, images);
+    }
+
+    pub fn template_sources(&self) -> &BTreeMap<String, TemplateSource> {
+        &self.sources
+    }
+
+    pub fn set_assets(&mut self, assets: BTreeMap<String, String>) {
+        self.assets = assets;
+    }
+
+    pub fn set_data(&mut self, data: tera::Value) {
+        self.data = data;
+    }
+
+    pub fn theme_config(&self) -> &toml::Table {
+        &self.theme_config
+    }
+
+    fn base_context(&self, config: &Config, lang: &str) -> Context {
+        let language = config.language(lang);
+        let languages: Vec<Language> = config.language_codes().into_iter().map(|code| config.language(code)).collect();
         let mut context = Context::new();
-        context.insert("post", post);
         context.insert("config", config);
-        Ok(self.tera.render("post.html", &context)?)
+        context.insert("lang", &language.code);
+        context.insert("language", &language);
+        context.insert("languages", &languages);
+        context.insert("theme_config", &self.theme_config);
+        context.insert("assets", &self.assets);
+        context.insert("data", &self.data);
+        context
+    }
+
+    fn render(&self, template: &str, context: &Context) -> Result<String> {
+        self.tera.render(template, context).map_err(|source| BlogError::Render {
+            template: template.to_string(),
+            message: match self.sources.get(template) {
+                Some(layer) => format!("Failed to render template {} from {}", template, layer),
+                None => format!(
+                    "Failed to render template {}: not found in any theme layer. Available templates:\n{}",
+                    template,
+                    describe_sources(&self.sources)
+                ),
+            },
+            source,
+        })
+    }
+
+    pub fn has_template(&self, name: &str) -> bool {
+        self.sources.contains_key(name)
+    }
+
+    pub fn post_template(&self, post: &Post, config: &Config) -> Result<String> {
+        let requested = post.meta.template.as_deref().or_else(|| {
+            post.section
+                .as_ref()
+                .and_then(|section| config.sections.get(section))
+                .and_then(|section| section.template.as_deref())
+        });
+
+        let Some(requested) = requested else {
+            return Ok("post.html".to_string());
+        };
+
+        let name = if Path::new(requested).extension().is_some() {
+            requested.to_string()
+        } else {
+            format!("{}.html", requested)
+        };
+
+        if !self.has_template(&name) {
+            return Err(BlogError::MissingTemplate {
+                slug: post.meta.slug.clone(),
+                title: post.meta.title.clone(),
+                template: name,
+            });
+        }
+        Ok(name)
+    }
+
+    pub fn render_post(&self, post: &Post, config: &Config) -> Result<String> {
+        let template = self.post_template(post, config)?;
+        let mut context = self.base_context(config, &post.lang);
+        context.insert("post", post);
+        context.insert("previous", &post.previous);
+        context.insert("next", &post.next);
+        context.insert("breadcrumbs", &post.breadcrumbs);
+        self.render(&template, &context)
     }
 
     pub fn render_index(&self, posts: &[Post], config: &Config) -> Result<String> {
-        let mut context = Context::new();
+        let mut context = self.base_context(config, config.default_language());
         context.insert("posts", posts);
-        context.insert("config", config);
-        Ok(self.tera.render("index.html", &context)?)
+        self.render("index.html", &context)
     }
 
-    pub fn render_paginated_index(&self, paginator: &crate::pagination::Paginator<Post>, config: &Config) -> Result<String> {
-        let mut context = Context::new();
+    pub fn render_paginated_index(&self, paginator: &crate::pagination::Paginator<Post>, config: &Config, lang: &str) -> Result<String> {
+        let mut context = self.base_context(config, lang);
         context.insert("paginator", paginator);
-        context.insert("config", config);
-        Ok(self.tera.render("index.html", &context)?)
+        self.render("index.html", &context)
     }
 
-    pub fn render_taxonomy(&self, name: &str, posts: &[&Post], config: &Config) -> Result<String> {
-        let mut context = Context::new();
+    pub fn render_taxonomy(&self, name: &str, posts: &[&Post], config: &Config, lang: &str) -> Result<String> {
+        let mut context = self.base_context(config, lang);
         context.insert("name", name);
         context.insert("posts", posts);
-        context.insert("config", config);
-        Ok(self.tera.render("taxonomy.html", &context)?)
+        self.render("taxonomy.html", &context)
+    }
+
+    pub fn render_series(&self, series: &crate::series::Series, config: &Config, lang: &str) -> Result<String> {
+        let mut context = self.base_context(config, lang);
+        context.insert("series", series);
+        context.insert("name", series.title);
+        context.insert("posts", &series.posts);
+        self.render(self.template_or("series.html", "taxonomy.html"), &context)
+    }
+
+    pub fn render_author(&self, author: &Author, posts: &[&Post], config: &Config, lang: &str) -> Result<String> {
+        let mut context = self.base_context(config, lang);
+        context.insert("author", author);
+        context.insert("name", &author.name);
+        context.insert("posts", posts);
+        self.render(self.template_or("author.html", "taxonomy.html"), &context)
+    }
+
+    fn template_or<'a>(&self, template: &'a str, fallback: &'a str) -> &'a str {
+        match self.has_template(template) {
+            true => template,
+            false => fallback,
+        }
     }
+
+    pub fn status_templates(&self) -> Vec<(u16, String)> {
+        self.sources
+            .keys()
+            .filter_map(|name| {
+                let code = name.strip_suffix(".html")?;
+                if code.len() != 3 {
+                    return None;
+                }
+                let status = code.parse::<u16>().ok().filter(|s| (100..600).contains(s))?;
+                Some((status, name.clone()))
+            })
+            .collect()
+    }
+
+    pub fn render_status_page(&self, status: u16, template: &str, posts: &[Post], config: &Config) -> Result<String> {
+        let mut context = self.base_context(config, config.default_language());
+        context.insert("status", &status);
+        context.insert("recent_posts", &posts[..posts.len().min(RECENT_POSTS)]);
+        context.insert("tags", &taxonomy_summary("tags", &crate::taxonomy::group_by_tag(posts)));
+        context.insert("categories", &taxonomy_summary("categories", &crate::taxonomy::group_by_category(posts)));
+        context.insert("search_url", "/search.json");
+        self.render(template, &context)
+    }
+}
+
+const RECENT_POSTS: usize = 5;
+
+#[derive(Serialize)]
+struct TaxonomySummary<'a> {
+    name: &'a str,
+    url: String,
+    count: usize,
+}
+
+fn taxonomy_summary<'a>(kind: &str, groups: &'a HashMap<String, Vec<&Post>>) -> Vec<TaxonomySummary<'a>> {
+    let mut summary: Vec<TaxonomySummary> = groups
+        .iter()
+        .map(|(name, posts)| TaxonomySummary {
+            name,
+            url: format!("/{}/{}/", kind, name),
+            count: posts.len(),
+        })
+        .collect();
+    summary.sort_by(|a, b| a.name.cmp(b.name));
+    summary
+}
+
+fn describe_sources(sources: &BTreeMap<String, TemplateSource>) -> String {
+    sources
+        .iter()
+        .map(|(name, source)| format!("  {} <- {}", name, source))
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::domain::PostMeta;
+    use crate::domain::{PostMeta, SectionConfig};
     use std::fs;
     use tempfile::TempDir;
 
@@ -73,24 +287,21 @@ mod tests {
                     title: "P1".to_string(),
                     date: "2023".to_string(),
                     slug: "p1".to_string(),
-                    tags: None,
-                    categories: None,
-                    draft: None,
-                    image: None,
+                    ..Default::default()
                 },
                 content: "".to_string(),
+                ..Default::default()
             }],
+            ..Default::default()
         };
 
         let config = Config {
             title: "Blog".to_string(),
             base_url: "url".to_string(),
-            description: None,
-            posts_per_page: None,
-            theme: None,
+            ..Default::default()
         };
 
-        let output = renderer.render_paginated_index(&paginator, &config).expect("Failed to render paginated index");
+        let output = renderer.render_paginated_index(&paginator, &config, "en").expect("Failed to render paginated index");
         assert!(output.contains("Page 1 of 2: P1"));
     }
 
@@ -110,23 +321,19 @@ mod tests {
                 title: "P1".to_string(),
                 date: "2023".to_string(),
                 slug: "p1".to_string(),
-                tags: None,
-                categories: None,
-                draft: None,
-                image: None,
+                ..Default::default()
             },
             content: "content".to_string(),
+            ..Default::default()
         };
 
         let config = Config {
             title: "My Blog".to_string(),
             base_url: "https://example.com".to_string(),
-            description: None,
-            posts_per_page: None,
-            theme: None,
+            ..Default::default()
         };
 
-        let output = renderer.render_taxonomy("rust", &[&post], &config).expect("Failed to render taxonomy");
+        let output = renderer.render_taxonomy("rust", &[&post], &config, "en").expect("Failed to render taxonomy");
 
         assert!(output.contains("<h1>Tag: rust</h1>"));
         assert!(output.contains("P1"));
@@ -143,7 +350,7 @@ mod tests {
         <head><title>{{ post.meta.title }} - {{ config.title }}</title></head>
         <body>
             <h1>{{ post.meta.title }}</h1>
-            <div class="content">{{ post.content }}</div>
+            <div class="content">{{ post.content | safe }}</div>
         </body>
         </html>"#;
         fs::write(theme_dir.join("post.html"), post_template).expect("Failed to write template");
@@ -155,20 +362,16 @@ mod tests {
                 title: "Hello World".to_string(),
                 date: "2023-01-01".to_string(),
                 slug: "hello-world".to_string(),
-                tags: None,
-                categories: None,
-                draft: None,
-                image: None,
+                ..Default::default()
             },
             content: "<p>This is content</p>".to_string(),
+            ..Default::default()
         };
 
         let config = Config {
             title: "My Blog".to_string(),
             base_url: "https://example.com".to_string(),
-            description: None,
-            posts_per_page: None,
-            theme: None,
+            ..Default::default()
         };
 
         let output = renderer.render_post(&post, &config).expect("Failed to render post");
@@ -177,4 +380,219 @@ mod tests {
         assert!(output.contains("<h1>Hello World</h1>"));
         assert!(output.contains("<div class=\"content\"><p>This is content</p></div>"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_render_with_theme_layers() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project = temp_dir.path();
+        fs::create_dir_all(project.join("themes/base")).unwrap();
+        fs::create_dir_all(project.join("themes/child")).unwrap();
+        fs::create_dir_all(project.join("templates")).unwrap();
+
+        fs::write(project.join("themes/base/base.html"), "[{% block body %}{% endblock %}]{% include \"footer.html\" %}").unwrap();
+        fs::write(project.join("themes/base/footer.html"), "base footer").unwrap();
+        fs::write(project.join("themes/child/theme.toml"), "parent = \"base\"").unwrap();
+        fs::write(project.join("themes/child/post.html"), "{% extends \"base.html\" %}{% block body %}{{ post.meta.title }}{% endblock %}").unwrap();
+        fs::write(project.join("templates/footer.html"), "project footer").unwrap();
+
+        let theme = Theme::load(project, "child").expect("Failed to load theme");
+        let renderer = Renderer::from_theme(&theme, &Config::default()).expect("Failed to create renderer");
+        assert_eq!(renderer.template_sources()["footer.html"].layer, "project templates");
+
+        let post = Post {
+            meta: PostMeta {
+                title: "Layered".to_string(),
+                date: "2023-01-01".to_string(),
+                slug: "layered".to_string(),
+                ..Default::default()
+            },
+            content: "".to_string(),
+            ..Default::default()
+        };
+        let config = Config {
+            title: "My Blog".to_string(),
+            base_url: "https://example.com".to_string(),
+            ..Default::default()
+        };
+
+        let output = renderer.render_post(&post, &config).expect("Failed to render post");
+        assert_eq!(output, "[Layered]project footer");
+
+        let err = renderer.render_taxonomy("rust", &[], &config, "en").unwrap_err();
+        let message = err.report();
+        assert!(message.contains("taxonomy.html: not found in any theme layer"), "{}", message);
+        assert!(message.contains("post.html <- "), "{}", message);
+    }
+
+    #[test]
+    fn test_render_post_with_custom_templates() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let theme_dir = temp_dir.path().join("templates");
+        fs::create_dir(&theme_dir).expect("Failed to create templates dir");
+        fs::write(theme_dir.join("post.html"), "default: {{ post.meta.title }}").unwrap();
+        fs::write(theme_dir.join("photo-essay.html"), "photo: {{ post.meta.title }}").unwrap();
+        fs::write(theme_dir.join("landing.html"), "landing: {{ post.meta.title }}").unwrap();
+
+        let renderer = Renderer::new(&theme_dir).expect("Failed to create renderer");
+
+        let mut config = Config {
+            title: "My Blog".to_string(),
+            base_url: "https://example.com".to_string(),
+            ..Default::default()
+        };
+        config.sections.insert(
+            "photos".to_string(),
+            SectionConfig {
+                template: Some("photo-essay.html".to_string()),
+            },
+        );
+
+        let mut post = Post {
+            meta: PostMeta {
+                title: "Trip".to_string(),
+                date: "2023-01-01".to_string(),
+                slug: "trip".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(renderer.render_post(&post, &config).unwrap(), "default: Trip");
+
+        post.section = Some("photos".to_string());
+        assert_eq!(renderer.render_post(&post, &config).unwrap(), "photo: Trip");
+
+        post.meta.template = Some("landing".to_string());
+        assert_eq!(renderer.render_post(&post, &config).unwrap(), "landing: Trip");
+
+        post.meta.template = Some("gallery.html".to_string());
+        let err = renderer.render_post(&post, &config).unwrap_err().to_string();
+        assert!(err.contains("Post `trip`"), "{}", err);
+        assert!(err.contains("`gallery.html`"), "{}", err);
+    }
+
+    #[test]
+    fn test_autoescape() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let theme_dir = temp_dir.path().join("templates");
+        fs::create_dir(&theme_dir).expect("Failed to create templates dir");
+        fs::write(theme_dir.join("post.html"), "<h1>{{ post.meta.title }}</h1>{{ post.content | safe }}").unwrap();
+
+        let post = Post {
+            meta: PostMeta {
+                title: "<script>alert(1)</script>".to_string(),
+                date: "2023-01-01".to_string(),
+                slug: "xss".to_string(),
+                ..Default::default()
+            },
+            content: "<p>Trusted</p>".to_string(),
+            ..Default::default()
+        };
+
+        let renderer = Renderer::new(&theme_dir).expect("Failed to create renderer");
+        let output = renderer.render_post(&post, &Config::default()).expect("Failed to render post");
+        assert_eq!(output, "<h1>&lt;script&gt;alert(1)&lt;&#x2F;script&gt;</h1><p>Trusted</p>");
+
+        let legacy = Config {
+            autoescape: Some(false),
+            ..Default::default()
+        };
+        let renderer = Renderer::from_theme(&Theme::from_dir(&theme_dir), &legacy).expect("Failed to create renderer");
+        let output = renderer.render_post(&post, &legacy).expect("Failed to render post");
+        assert_eq!(output, "<h1><script>alert(1)</script></h1><p>Trusted</p>");
+    }
+
+    #[test]
+    fn test_theme_config_in_context() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project = temp_dir.path();
+        fs::create_dir_all(project.join("themes/default")).unwrap();
+        fs::write(
+            project.join("themes/default/theme.toml"),
+            "[options.accent]\ntype = \"string\"\ndefault = \"blue\"\n\n[options.sidebar]\ntype = \"bool\"\ndefault = false\n",
+        )
+        .unwrap();
+        fs::write(project.join("themes/default/post.html"), "{{ theme_config.accent }} {{ theme_config.sidebar }}").unwrap();
+
+        let mut config = Config::default();
+        config.theme_config.insert("sidebar".to_string(), toml::Value::Boolean(true));
+
+        let theme = Theme::load(project, "default").expect("Failed to load theme");
+        let renderer = Renderer::from_theme(&theme, &config).expect("Failed to create renderer");
+        let output = renderer.render_post(&Post::default(), &config).expect("Failed to render post");
+        assert_eq!(output, "blue true");
+
+        config.theme_config.insert("accent".to_string(), toml::Value::Integer(3));
+        assert!(Renderer::from_theme(&theme, &config).is_err());
+    }
+
+    #[test]
+    fn test_render_status_pages() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let theme_dir = temp_dir.path().join("templates");
+        fs::create_dir(&theme_dir).expect("Failed to create templates dir");
+        fs::write(
+            theme_dir.join("404.html"),
+            "{{ status }}|{% for p in recent_posts %}{{ p.meta.title }},{% endfor %}|{% for t in tags %}{{ t.name }}={{ t.count }}{% endfor %}|{{ search_url }}",
+        )
+        .unwrap();
+        fs::write(theme_dir.join("500.html"), "{{ status }}").unwrap();
+        fs::write(theme_dir.join("post.html"), "").unwrap();
+        fs::write(theme_dir.join("1234.html"), "").unwrap();
+
+        let renderer = Renderer::new(&theme_dir).expect("Failed to create renderer");
+        assert_eq!(
+            renderer.status_templates(),
+            vec![(404, "404.html".to_string()), (500, "500.html".to_string())]
+        );
+
+        let posts: Vec<Post> = (1..=7)
+            .map(|i| Post {
+                meta: PostMeta {
+                    title: format!("P{}", i),
+                    tags: Some(vec!["rust".to_string()]),
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .collect();
+
+        let output = renderer
+            .render_status_page(404, "404.html", &posts, &Config::default())
+            .expect("Failed to render 404 page");
+        assert_eq!(output, "404|P1,P2,P3,P4,P5,|rust=7|&#x2F;search.json");
+    }
+
+    #[test]
+    fn test_render_post_navigation() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let theme_dir = temp_dir.path().join("templates");
+        fs::create_dir(&theme_dir).expect("Failed to create templates dir");
+        fs::write(
+            theme_dir.join("post.html"),
+            "{% for b in breadcrumbs %}{{ b.title }} > {% endfor %}{% if previous %}older: {{ previous.title }}{% endif %}{% if next %}newer: {{ next.title }}{% endif %}",
+        )
+        .unwrap();
+        let renderer = Renderer::new(&theme_dir).expect("Failed to create renderer");
+
+        let mut posts: Vec<Post> = ["C", "B", "A"]
+            .iter()
+            .map(|title| Post {
+                meta: PostMeta {
+                    title: title.to_string(),
+                    slug: title.to_lowercase(),
+                    ..Default::default()
+                },
+                url: format!("/posts/{}/", title.to_lowercase()),
+                ..Default::default()
+            })
+            .collect();
+        let config = Config {
+            title: "Blog".to_string(),
+            ..Default::default()
+        };
+        crate::navigation::link_neighbours(&mut posts, &config).unwrap();
+
+        let output = renderer.render_post(&posts[1], &config).expect("Failed to render post");
+        assert_eq!(output, "Blog > B > older: Anewer: C");
+    }
+}
@@ -1,50 +1,105 @@
-use crate::domain::{Config, Post};
-use anyhow::Result;
+use crate::domain::{Author, Config, Post};
+use crate::error::Result;
 
-pub fn generate_sitemap(posts: &[Post], config: &Config) -> Result<String> {
+pub fn generate_sitemap(posts: &[Post], config: &Config, lang: &str) -> Result<String> {
     let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>
-<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" xmlns:xhtml="http://www.w3.org/1999/xhtml">
 "#);
 
-    xml.push_str(&format!("  <url><loc>{}/</loc></url>\n", config.base_url.trim_end_matches('/')));
+    let base_url = escape_xml(config.base_url.trim_end_matches('/'));
+    let codes = config.language_codes();
+    let homes: Vec<(&str, String)> = match codes.len() {
+        1 => Vec::new(),
+        _ => codes.iter().map(|code| (*code, config.language(code).url)).collect(),
+    };
+    xml.push_str(&sitemap_url(&base_url, &config.language(lang).url, &homes));
 
     for post in posts {
-        xml.push_str(&format!(
-            "  <url><loc>{}/posts/{}/</loc></url>\n",
-            config.base_url.trim_end_matches('/'),
-            post.meta.slug
-        ));
+        let alternates: Vec<(&str, String)> = match post.translations.is_empty() {
+            true => Vec::new(),
+            false => std::iter::once((post.lang.as_str(), post.url.clone()))
+                .chain(post.translations.iter().map(|t| (t.lang.as_str(), t.url.clone())))
+                .collect(),
+        };
+        xml.push_str(&sitemap_url(&base_url, &post.url, &alternates));
     }
 
     xml.push_str("</urlset>");
     Ok(xml)
 }
 
-pub fn generate_rss(posts: &[Post], config: &Config) -> Result<String> {
+fn sitemap_url(base_url: &str, path: &str, alternates: &[(&str, String)]) -> String {
+    let mut entry = format!("  <url><loc>{}{}</loc>", base_url, escape_xml(path));
+    for (lang, url) in alternates {
+        entry.push_str(&format!(
+            "<xhtml:link rel=\"alternate\" hreflang=\"{}\" href=\"{}{}\"/>",
+            escape_xml(lang),
+            base_url,
+            escape_xml(url)
+        ));
+    }
+    entry.push_str("</url>\n");
+    entry
+}
+
+pub fn generate_rss(posts: &[Post], config: &Config, lang: &str) -> Result<String> {
+    let language = config.language(lang);
+    let description = language.description.as_deref().unwrap_or("");
+    Ok(rss_channel(posts, config, &language.title, &language.url, description))
+}
+
+pub fn generate_author_rss(author: &Author, posts: &[&Post], config: &Config, lang: &str) -> Result<String> {
+    let language = config.language(lang);
+    let title = format!("{} - {}", language.title, author.name);
+    let description = author.bio.as_deref().or(language.description.as_deref()).unwrap_or("");
+    Ok(rss_channel(posts.iter().copied(), config, &title, &author.url, description))
+}
+
+fn rss_channel<'a>(posts: impl IntoIterator<Item = &'a Post>, config: &Config, title: &str, path: &str, description: &str) -> String {
     let mut rss = String::from(r#"<?xml version="1.0" encoding="UTF-8" ?>
-<rss version="2.0">
+<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
 <channel>
 "#);
 
-    rss.push_str(&format!("  <title>{}</title>\n", config.title));
-    rss.push_str(&format!("  <link>{}</link>\n", config.base_url.trim_end_matches('/')));
-    rss.push_str(&format!("  <description>{}</description>\n", config.description.as_deref().unwrap_or("")));
+    let base_url = escape_xml(config.base_url.trim_end_matches('/'));
+    rss.push_str(&format!("  <title>{}</title>\n", escape_xml(title)));
+    rss.push_str(&format!("  <link>{}{}</link>\n", base_url, escape_xml(path.trim_end_matches('/'))));
+    rss.push_str(&format!("  <description>{}</description>\n", escape_xml(description)));
 
     for post in posts {
+        let url = escape_xml(&post.url);
         rss.push_str("  <item>\n");
-        rss.push_str(&format!("    <title>{}</title>\n", post.meta.title));
-        rss.push_str(&format!(
-            "    <link>{}/posts/{}/</link>\n",
-            config.base_url.trim_end_matches('/'),
-            post.meta.slug
-        ));
-        rss.push_str(&format!("    <guid>{}/posts/{}/</guid>\n", config.base_url.trim_end_matches('/'), post.meta.slug));
-        rss.push_str(&format!("    <pubDate>{}</pubDate>\n", post.meta.date));
+        rss.push_str(&format!("    <title>{}</title>\n", escape_xml(&post.meta.title)));
+        rss.push_str(&format!("    <link>{}{}</link>\n", base_url, url));
+        rss.push_str(&format!("    <guid>{}{}</guid>\n", base_url, url));
+        rss.push_str(&format!("    <pubDate>{}</pubDate>\n", escape_xml(&post.meta.date)));
+        if let Some(author) = post.authors.iter().find(|a| a.email.is_some()) {
+            let email = author.email.as_deref().unwrap_or_default();
+            rss.push_str(&format!("    <author>{} ({})</author>\n", escape_xml(email), escape_xml(&author.name)));
+        }
+        for author in &post.authors {
+            rss.push_str(&format!("    <dc:creator>{}</dc:creator>\n", escape_xml(&author.name)));
+        }
         rss.push_str("  </item>\n");
     }
 
     rss.push_str("</channel>\n</rss>");
-    Ok(rss)
+    rss
+}
+
+pub fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 use serde::Serialize;
@@ -79,9 +134,7 @@ mod tests {
         let config = Config {
             title: "Test Blog".to_string(),
             base_url: "https://example.com".to_string(),
-            description: None,
-            posts_per_page: None,
-            theme: None,
+            ..Default::default()
         };
 
         let posts = vec![
@@ -90,16 +143,15 @@ mod tests {
                     title: "Post 1".to_string(),
                     date: "2023-01-01".to_string(),
                     slug: "post-1".to_string(),
-                    tags: None,
-                    categories: None,
-                    draft: None,
-                    image: None,
+                    ..Default::default()
                 },
                 content: "content".to_string(),
+                url: "/posts/post-1/".to_string(),
+                ..Default::default()
             },
         ];
 
-        let sitemap = generate_sitemap(&posts, &config).expect("Failed to generate sitemap");
+        let sitemap = generate_sitemap(&posts, &config, "en").expect("Failed to generate sitemap");
 
         assert!(sitemap.contains("<loc>https://example.com/</loc>"));
         assert!(sitemap.contains("<loc>https://example.com/posts/post-1/</loc>"));
@@ -111,8 +163,7 @@ mod tests {
             title: "Test Blog".to_string(),
             base_url: "https://example.com".to_string(),
             description: Some("A test blog".to_string()),
-            posts_per_page: None,
-            theme: None,
+            ..Default::default()
         };
 
         let posts = vec![
@@ -121,16 +172,15 @@ mod tests {
                     title: "Post 1".to_string(),
                     date: "2023-01-01".to_string(),
                     slug: "post-1".to_string(),
-                    tags: None,
-                    categories: None,
-                    draft: None,
-                    image: None,
+                    ..Default::default()
                 },
                 content: "content".to_string(),
+                url: "/posts/post-1/".to_string(),
+                ..Default::default()
             },
         ];
 
-        let rss = generate_rss(&posts, &config).expect("Failed to generate RSS");
+        let rss = generate_rss(&posts, &config, "en").expect("Failed to generate RSS");
 
         assert!(rss.contains("<title>Test Blog</title>"));
         assert!(rss.contains("<link>https://example.com</link>"));
@@ -138,6 +188,31 @@ mod tests {
         assert!(rss.contains("https://example.com/posts/post-1/"));
     }
 
+    #[test]
+    fn test_generate_rss_escapes_markup() {
+        let config = Config {
+            title: "Tips & Tricks".to_string(),
+            base_url: "https://example.com".to_string(),
+            ..Default::default()
+        };
+
+        let posts = vec![Post {
+            meta: PostMeta {
+                title: "<script>alert(1)</script>".to_string(),
+                date: "2023-01-01".to_string(),
+                slug: "xss".to_string(),
+                ..Default::default()
+            },
+            ..Default::default()
+        }];
+
+        let rss = generate_rss(&posts, &config, "en").expect("Failed to generate RSS");
+
+        assert!(rss.contains("<title>Tips &amp; Tricks</title>"));
+        assert!(rss.contains("<title>&lt;script&gt;alert(1)&lt;/script&gt;</title>"));
+        assert!(!rss.contains("<script>"));
+    }
+
     #[test]
     fn test_generate_search_index() {
         let posts = vec![
@@ -147,11 +222,10 @@ mod tests {
                     date: "2023-01-01".to_string(),
                     slug: "searchable-post".to_string(),
                     tags: Some(vec!["rust".to_string()]),
-                    categories: None,
-                    draft: None,
-                    image: None,
+                    ..Default::default()
                 },
                 content: "This is searchable content".to_string(),
+                ..Default::default()
             },
         ];
 
@@ -159,4 +233,43 @@ mod tests {
         assert!(index_json.contains("Searchable Post"));
         assert!(index_json.contains("searchable-post"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_generate_rss_authors() {
+        let config = Config {
+            title: "Test Blog".to_string(),
+            base_url: "https://example.com".to_string(),
+            ..Default::default()
+        };
+        let ada = Author {
+            id: "ada".to_string(),
+            name: "Ada Lovelace".to_string(),
+            url: "/authors/ada/".to_string(),
+            email: Some("ada@example.com".to_string()),
+            bio: Some("Analyst & writer".to_string()),
+            ..Default::default()
+        };
+        let grace = Author {
+            name: "Grace Hopper".to_string(),
+            ..Default::default()
+        };
+        let post = Post {
+            meta: PostMeta {
+                title: "Post 1".to_string(),
+                slug: "post-1".to_string(),
+                ..Default::default()
+            },
+            authors: vec![grace, ada.clone()],
+            ..Default::default()
+        };
+
+        let rss = generate_rss(std::slice::from_ref(&post), &config, "en").expect("Failed to generate RSS");
+        assert!(rss.contains("<author>ada@example.com (Ada Lovelace)</author>"), "{}", rss);
+        assert!(rss.contains("<dc:creator>Grace Hopper</dc:creator>\n    <dc:creator>Ada Lovelace</dc:creator>"), "{}", rss);
+
+        let rss = generate_author_rss(&ada, &[&post], &config, "en").expect("Failed to generate RSS");
+        assert!(rss.contains("<title>Test Blog - Ada Lovelace</title>"));
+        assert!(rss.contains("<link>https://example.com/authors/ada</link>"));
+        assert!(rss.contains("<description>Analyst &amp; writer</description>"));
+    }
+}
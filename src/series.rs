@@ -0,0 +1,124 @@
+use crate::domain::{Config, Post, PostLink, SeriesInfo};
+use crate::functions::slugify;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Serialize)]
+pub struct Series<'a> {
+    pub title: &'a str,
+    pub slug: String,
+    pub url: String,
+    pub posts: Vec<&'a Post>,
+}
+
+pub fn series_url(config: &Config, lang: &str, slug: &str) -> String {
+    format!("{}/series/{}/", config.language_prefix(lang), slug)
+}
+
+pub fn group_by_series<'a>(posts: &'a [Post], config: &Config) -> Vec<Series<'a>> {
+    groups(posts)
+        .into_iter()
+        .map(|(slug, parts)| Series {
+            title: posts[parts[0]].meta.series.as_deref().unwrap_or_default(),
+            url: series_url(config, &posts[parts[0]].lang, &slug),
+            slug,
+            posts: parts.into_iter().map(|i| &posts[i]).collect(),
+        })
+        .collect()
+}
+
+pub fn link_series(posts: &mut [Post], config: &Config) {
+    let summaries: Vec<PostLink> = posts.iter().map(PostLink::of).collect();
+    for post in posts.iter_mut() {
+        post.series = None;
+    }
+    for (slug, parts) in groups(posts) {
+        let title = posts[parts[0]].meta.series.clone().unwrap_or_default();
+        let url = series_url(config, &posts[parts[0]].lang, &slug);
+        let links: Vec<PostLink> = parts.iter().map(|&i| summaries[i].clone()).collect();
+        for (k, &i) in parts.iter().enumerate() {
+            posts[i].series = Some(SeriesInfo {
+                title: title.clone(),
+                slug: slug.clone(),
+                url: url.clone(),
+                parts: links.clone(),
+                index: k + 1,
+                total: parts.len(),
+                previous: k.checked_sub(1).map(|k| links[k].clone()),
+                next: links.get(k + 1).cloned(),
+            });
+        }
+    }
+}
+
+fn groups(posts: &[Post]) -> BTreeMap<String, Vec<usize>> {
+    let mut groups: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, post) in posts.iter().enumerate() {
+        let Some(title) = post.meta.series.as_deref() else {
+            continue;
+        };
+        let slug = slugify(title);
+        if !slug.is_empty() {
+            groups.entry(slug).or_default().push(i);
+        }
+    }
+    for parts in groups.values_mut() {
+        parts.sort_by(|&a, &b| {
+            let (a, b) = (&posts[a].meta, &posts[b].meta);
+            let order = |order: Option<u32>| order.map_or(u64::MAX, u64::from);
+            order(a.series_order)
+                .cmp(&order(b.series_order))
+                .then_with(|| a.date.cmp(&b.date))
+                .then_with(|| a.slug.cmp(&b.slug))
+        });
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(slug: &str, date: &str, series: Option<&str>, order: Option<u32>) -> Post {
+        let mut post = Post::fixture(slug);
+        post.meta.date = date.to_string();
+        post.meta.series = series.map(str::to_string);
+        post.meta.series_order = order;
+        post
+    }
+
+    #[test]
+    fn test_link_series() {
+        let mut posts = vec![
+            post("setup", "2023-03-01", Some("Rust Tutorial"), Some(1)),
+            post("aside", "2023-02-15", None, None),
+            post("wrap-up", "2023-02-10", Some("rust tutorial"), None),
+            post("basics", "2023-02-01", Some("Rust Tutorial"), Some(2)),
+            post("other", "2023-01-01", Some("Cooking"), None),
+        ];
+        let config = Config::default();
+        link_series(&mut posts, &config);
+
+        let series = posts[3].series.as_ref().expect("Missing series");
+        assert_eq!(series.title, "Rust Tutorial");
+        assert_eq!(series.slug, "rust-tutorial");
+        assert_eq!(series.url, "/series/rust-tutorial/");
+        let parts: Vec<&str> = series.parts.iter().map(|p| p.slug.as_str()).collect();
+        assert_eq!(parts, vec!["setup", "basics", "wrap-up"]);
+        assert_eq!((series.index, series.total), (2, 3));
+        assert_eq!(series.previous.as_ref().map(|p| p.slug.as_str()), Some("setup"));
+        assert_eq!(series.next.as_ref().map(|p| p.slug.as_str()), Some("wrap-up"));
+
+        let first = posts[0].series.as_ref().unwrap();
+        assert_eq!((first.index, first.previous.is_none()), (1, true));
+        assert!(posts[2].series.as_ref().unwrap().next.is_none());
+        assert!(posts[1].series.is_none());
+        assert_eq!(posts[4].series.as_ref().unwrap().total, 1);
+
+        let groups = group_by_series(&posts, &config);
+        let slugs: Vec<&str> = groups.iter().map(|s| s.slug.as_str()).collect();
+        assert_eq!(slugs, vec!["cooking", "rust-tutorial"]);
+        let parts: Vec<&str> = groups[1].posts.iter().map(|p| p.meta.slug.as_str()).collect();
+        assert_eq!(parts, vec!["setup", "basics", "wrap-up"]);
+    }
+}
@@ -1,17 +1,23 @@
-use crate::engine::build_site;
-use anyhow::Result;
+use crate::config::load_config;
+use crate::engine::{build_site_with_options, builds_dir, staging_dir, BuildOptions};
+use crate::error::{IoResultExt, Result};
 use axum::Router;
 use notify_debouncer_mini::{new_debouncer, notify::*};
 use std::path::Path;
 use std::sync::mpsc::channel;
 use std::time::Duration;
-use tower_http::services::ServeDir;
+use tower_http::services::{ServeDir, ServeFile};
 
 pub async fn serve<P: AsRef<Path>>(input: P, output: P, port: u16, include_drafts: bool) -> Result<()> {
     let input = input.as_ref().to_path_buf();
     let output = output.as_ref().to_path_buf();
 
-    build_site(&input, &output, include_drafts)?;
+    let options = BuildOptions {
+        include_drafts,
+        dev: true,
+        ..Default::default()
+    };
+    build_site_with_options(&input, &output, &options)?;
 
     let (tx, rx) = channel();
     let mut debouncer = new_debouncer(Duration::from_millis(500), tx)?;
@@ -20,13 +26,26 @@ pub async fn serve<P: AsRef<Path>>(input: P, output: P, port: u16, include_draft
 
     let input_cloned = input.clone();
     let output_cloned = output.clone();
+    let output_abs = output.canonicalize().unwrap_or_else(|_| output.clone());
+    let input_abs = input.canonicalize().unwrap_or_else(|_| input.clone());
+    let mut ignored = vec![
+        staging_dir(&output_abs)?,
+        builds_dir(&output_abs)?,
+        output_abs,
+        input_abs.join(".cache"),
+    ];
+    // The link report is written into the project by every build.
+    if let Some(report) = load_config(input.join("config.toml"))?.links.report {
+        ignored.push(input_abs.join(report));
+    }
 
     tokio::task::spawn_blocking(move || {
         while let Ok(res) = rx.recv() {
             match res {
+                Ok(events) if events.iter().all(|e| ignored.iter().any(|dir| e.path.starts_with(dir))) => {}
                 Ok(_) => {
-                    if let Err(e) = build_site(&input_cloned, &output_cloned, include_drafts) {
-                        tracing::error!("Rebuild failed: {}", e);
+                    if let Err(e) = build_site_with_options(&input_cloned, &output_cloned, &options) {
+                        tracing::error!("Rebuild failed: {}", e.report());
                     } else {
                         tracing::info!("Site rebuilt successfully.");
                     }
@@ -36,10 +55,16 @@ pub async fn serve<P: AsRef<Path>>(input: P, output: P, port: u16, include_draft
         }
     });
 
-    let app = Router::new().fallback_service(ServeDir::new(&output));
-    let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
+    let not_found = ServeFile::new(output.join("404.html")).precompressed_br().precompressed_gzip();
+    let files = ServeDir::new(&output)
+        .precompressed_br()
+        .precompressed_gzip()
+        .not_found_service(not_found);
+    let app = Router::new().fallback_service(files);
+    let address = format!("0.0.0.0:{}", port);
+    let listener = tokio::net::TcpListener::bind(&address).await.io("bind", &address)?;
     tracing::info!("Serving blog at http://localhost:{}", port);
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app).await.io("serve", &output)?;
 
     Ok(())
 }
\ No newline at end of file
@@ -39,11 +39,10 @@ mod tests {
                     date: "2023".to_string(),
                     slug: "p1".to_string(),
                     tags: Some(vec!["rust".to_string(), "web".to_string()]),
-                    categories: None,
-                    draft: None,
-                    image: None,
+                    ..Default::default()
                 },
                 content: "".to_string(),
+                ..Default::default()
             },
             Post {
                 meta: PostMeta {
@@ -51,11 +50,10 @@ mod tests {
                     date: "2023".to_string(),
                     slug: "p2".to_string(),
                     tags: Some(vec!["rust".to_string()]),
-                    categories: None,
-                    draft: None,
-                    image: None,
+                    ..Default::default()
                 },
                 content: "".to_string(),
+                ..Default::default()
             },
         ];
 
@@ -0,0 +1,413 @@
+use crate::error::{BlogError, IoResultExt, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct ThemeManifest {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub min_engine_version: Option<String>,
+    pub parent: Option<String>,
+    #[serde(default)]
+    pub required_templates: Vec<String>,
+    #[serde(default)]
+    pub options: BTreeMap<String, ThemeOption>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+pub struct ThemeOption {
+    #[serde(rename = "type")]
+    pub kind: Option<OptionKind>,
+    pub default: Option<toml::Value>,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OptionKind {
+    String,
+    Bool,
+    Integer,
+    Float,
+    Array,
+    Table,
+}
+
+impl OptionKind {
+    fn matches(self, value: &toml::Value) -> bool {
+        matches!(
+            (self, value),
+            (OptionKind::String, toml::Value::String(_))
+                | (OptionKind::Bool, toml::Value::Boolean(_))
+                | (OptionKind::Integer, toml::Value::Integer(_))
+                | (OptionKind::Float, toml::Value::Float(_) | toml::Value::Integer(_))
+                | (OptionKind::Array, toml::Value::Array(_))
+                | (OptionKind::Table, toml::Value::Table(_))
+        )
+    }
+}
+
+impl fmt::Display for OptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OptionKind::String => "string",
+            OptionKind::Bool => "bool",
+            OptionKind::Integer => "integer",
+            OptionKind::Float => "float",
+            OptionKind::Array => "array",
+            OptionKind::Table => "table",
+        };
+        f.write_str(name)
+    }
+}
+
+pub fn load_manifest<P: AsRef<Path>>(theme_dir: P) -> Result<ThemeManifest> {
+    let path = theme_dir.as_ref().join("theme.toml");
+    if !path.exists() {
+        return Ok(ThemeManifest::default());
+    }
+    let content = fs::read_to_string(&path).io("read theme manifest", &path)?;
+    toml::from_str(&content).map_err(|source| BlogError::ThemeManifest { path, source })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateLayer {
+    pub label: String,
+    pub dir: PathBuf,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemplateSource {
+    pub layer: String,
+    pub path: PathBuf,
+}
+
+impl fmt::Display for TemplateSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.path.display(), self.layer)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub layers: Vec<TemplateLayer>,
+    pub manifests: Vec<(String, ThemeManifest)>,
+}
+
+impl Theme {
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Self {
+        let dir = dir.as_ref().to_path_buf();
+        let name = dir
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Self {
+            layers: vec![TemplateLayer {
+                label: format!("theme `{}`", name),
+                dir,
+            }],
+            name,
+            manifests: Vec::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(project_dir: P, name: &str) -> Result<Self> {
+        let project_dir = project_dir.as_ref();
+        let mut layers = Vec::new();
+
+        let overrides = project_dir.join("templates");
+        if overrides.is_dir() {
+            layers.push(TemplateLayer {
+                label: "project templates".to_string(),
+                dir: overrides,
+            });
+        }
+
+        let mut manifests = Vec::new();
+        let mut seen: Vec<String> = Vec::new();
+        let mut current = Some(name.to_string());
+        while let Some(theme_name) = current {
+            if seen.contains(&theme_name) {
+                seen.push(theme_name);
+                return Err(BlogError::Theme {
+                    theme: name.to_string(),
+                    message: format!("Theme inheritance cycle: {}", seen.join(" -> ")),
+                });
+            }
+
+            let dir = project_dir.join("themes").join(&theme_name);
+            if !dir.is_dir() {
+                let message = match seen.last() {
+                    Some(child) => format!("Theme `{}` (parent of `{}`) not found at {:?}", theme_name, child, dir),
+                    None => format!("Theme `{}` not found at {:?}", theme_name, dir),
+                };
+                return Err(BlogError::Theme {
+                    theme: name.to_string(),
+                    message,
+                });
+            }
+
+            let manifest = load_manifest(&dir)?;
+            layers.push(TemplateLayer {
+                label: format!("theme `{}`", theme_name),
+                dir,
+            });
+            current = manifest.parent.clone();
+            manifests.push((theme_name.clone(), manifest));
+            seen.push(theme_name);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            layers,
+            manifests,
+        })
+    }
+
+    pub fn resolve_templates(&self) -> Result<BTreeMap<String, TemplateSource>> {
+        let mut resolved = BTreeMap::new();
+        for layer in &self.layers {
+            for entry in WalkDir::new(&layer.dir).into_iter().filter_map(|e| e.ok()) {
+                let path = entry.path();
+                if !entry.file_type().is_file() || path.extension().is_none_or(|ext| ext != "html") {
+                    continue;
+                }
+                let relative = path.strip_prefix(&layer.dir).unwrap_or(path);
+                let name = relative
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                resolved.entry(name).or_insert_with(|| TemplateSource {
+                    layer: layer.label.clone(),
+                    path: path.to_path_buf(),
+                });
+            }
+        }
+        Ok(resolved)
+    }
+
+    pub fn validate(&self, templates: &BTreeMap<String, TemplateSource>, theme_config: &toml::Table) -> Result<toml::Table> {
+        let mut errors = Vec::new();
+
+        for (theme_name, manifest) in &self.manifests {
+            if let Some(required) = &manifest.min_engine_version {
+                match (parse_version(required), parse_version(ENGINE_VERSION)) {
+                    (Some(required_version), Some(engine_version)) if engine_version < required_version => errors.push(format!(
+                        "theme `{}` requires blog-engine >= {}, but this is {}",
+                        theme_name, required, ENGINE_VERSION
+                    )),
+                    (None, _) => errors.push(format!(
+                        "theme `{}` has an invalid min_engine_version `{}`",
+                        theme_name, required
+                    )),
+                    _ => {}
+                }
+            }
+
+            for template in &manifest.required_templates {
+                if !templates.contains_key(template) {
+                    errors.push(format!("theme `{}` requires template `{}`, which was not found in any layer", theme_name, template));
+                }
+            }
+        }
+
+        let mut schema: BTreeMap<&String, (&String, &ThemeOption)> = BTreeMap::new();
+        for (theme_name, manifest) in self.manifests.iter().rev() {
+            for (key, option) in &manifest.options {
+                schema.insert(key, (theme_name, option));
+            }
+        }
+
+        for (key, value) in theme_config {
+            match schema.get(key) {
+                None => errors.push(format!(
+                    "unknown theme option `{}`; theme `{}` declares: {}",
+                    key,
+                    self.name,
+                    if schema.is_empty() {
+                        "no options".to_string()
+                    } else {
+                        schema.keys().map(|k| k.as_str()).collect::<Vec<_>>().join(", ")
+                    }
+                )),
+                Some((_, option)) => {
+                    if let Some(kind) = option.kind.filter(|kind| !kind.matches(value)) {
+                        errors.push(format!("theme option `{}` must be a {}, got {}", key, kind, value.type_str()));
+                    }
+                }
+            }
+        }
+
+        let mut resolved = toml::Table::new();
+        for (key, (theme_name, option)) in &schema {
+            match theme_config.get(*key).or(option.default.as_ref()) {
+                Some(value) => {
+                    resolved.insert((*key).clone(), value.clone());
+                }
+                None if option.required => errors.push(format!(
+                    "theme option `{}` (declared by theme `{}`) is required; set it under [theme_config] in config.toml",
+                    key, theme_name
+                )),
+                None => {}
+            }
+            if let (Some(kind), Some(default)) = (option.kind, &option.default) {
+                if !kind.matches(default) {
+                    errors.push(format!(
+                        "theme `{}` declares option `{}` as {} but its default is a {}",
+                        theme_name,
+                        key,
+                        kind,
+                        default.type_str()
+                    ));
+                }
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(BlogError::Theme {
+                theme: self.name.clone(),
+                message: format!("Invalid theme `{}`:\n  - {}", self.name, errors.join("\n  - ")),
+            });
+        }
+        Ok(resolved)
+    }
+}
+
+const ENGINE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim().trim_start_matches('v').split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().map_or(Some(0), |p| p.parse().ok())?;
+    let patch = parts.next().map_or(Some(0), |p| p.split(['-', '+']).next()?.parse().ok())?;
+    Some((major, minor, patch))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: PathBuf, content: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_templates_with_overrides_and_parent() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project = temp_dir.path();
+
+        write(project.join("themes/base/index.html"), "base index");
+        write(project.join("themes/base/post.html"), "base post");
+        write(project.join("themes/base/partials/footer.html"), "base footer");
+        write(project.join("themes/child/theme.toml"), "parent = \"base\"\n");
+        write(project.join("themes/child/post.html"), "child post");
+        write(project.join("templates/partials/footer.html"), "project footer");
+
+        let theme = Theme::load(project, "child").expect("Failed to load theme");
+        let labels: Vec<&str> = theme.layers.iter().map(|l| l.label.as_str()).collect();
+        assert_eq!(labels, vec!["project templates", "theme `child`", "theme `base`"]);
+
+        let resolved = theme.resolve_templates().expect("Failed to resolve templates");
+        assert_eq!(resolved["index.html"].layer, "theme `base`");
+        assert_eq!(resolved["post.html"].layer, "theme `child`");
+        assert_eq!(resolved["partials/footer.html"].layer, "project templates");
+    }
+
+    #[test]
+    fn test_theme_errors() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project = temp_dir.path();
+
+        write(project.join("themes/a/theme.toml"), "parent = \"b\"\n");
+        write(project.join("themes/b/theme.toml"), "parent = \"a\"\n");
+        write(project.join("themes/orphan/theme.toml"), "parent = \"missing\"\n");
+
+        let err = Theme::load(project, "a").unwrap_err().to_string();
+        assert!(err.contains("a -> b -> a"), "{}", err);
+
+        let err = Theme::load(project, "orphan").unwrap_err().to_string();
+        assert!(err.contains("`missing` (parent of `orphan`)"), "{}", err);
+
+        assert!(Theme::load(project, "nope").is_err());
+    }
+
+    #[test]
+    fn test_validate_manifest_and_options() {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project = temp_dir.path();
+
+        write(
+            project.join("themes/base/theme.toml"),
+            r#"
+                name = "Base"
+                version = "1.2.0"
+                min_engine_version = "0.1.0"
+                required_templates = ["index.html"]
+
+                [options.accent_color]
+                type = "string"
+                default = "336699"
+
+                [options.show_reading_time]
+                type = "bool"
+                default = true
+            "#,
+        );
+        write(project.join("themes/base/index.html"), "index");
+        write(
+            project.join("themes/child/theme.toml"),
+            r#"
+                parent = "base"
+                required_templates = ["post.html"]
+
+                [options.show_reading_time]
+                type = "bool"
+                default = false
+
+                [options.analytics_id]
+                type = "string"
+                required = true
+            "#,
+        );
+        write(project.join("themes/child/post.html"), "post");
+
+        let theme = Theme::load(project, "child").expect("Failed to load theme");
+        let templates = theme.resolve_templates().unwrap();
+
+        let site_config: toml::Table = toml::from_str("analytics_id = \"UA-1\"").unwrap();
+        let resolved = theme.validate(&templates, &site_config).expect("Theme should be valid");
+        assert_eq!(resolved["accent_color"].as_str(), Some("336699"));
+        assert_eq!(resolved["show_reading_time"].as_bool(), Some(false));
+        assert_eq!(resolved["analytics_id"].as_str(), Some("UA-1"));
+
+        let site_config: toml::Table = toml::from_str("show_reading_time = \"yes\"\nsidebar = true").unwrap();
+        let err = theme.validate(&templates, &site_config).unwrap_err().to_string();
+        assert!(err.contains("unknown theme option `sidebar`"), "{}", err);
+        assert!(err.contains("`show_reading_time` must be a bool, got string"), "{}", err);
+        assert!(err.contains("`analytics_id` (declared by theme `child`) is required"), "{}", err);
+
+        write(project.join("themes/base/theme.toml"), "min_engine_version = \"99.0.0\"\nrequired_templates = [\"404.html\"]");
+        let theme = Theme::load(project, "base").expect("Failed to load theme");
+        let err = theme.validate(&theme.resolve_templates().unwrap(), &toml::Table::new()).unwrap_err().to_string();
+        assert!(err.contains("requires blog-engine >= 99.0.0"), "{}", err);
+        assert!(err.contains("requires template `404.html`"), "{}", err);
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(parse_version("1.2.3"), Some((1, 2, 3)));
+        assert_eq!(parse_version("v0.4"), Some((0, 4, 0)));
+        assert_eq!(parse_version("1.0.0-beta.1"), Some((1, 0, 0)));
+        assert_eq!(parse_version("latest"), None);
+    }
+}